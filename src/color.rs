@@ -0,0 +1,57 @@
+//! Optional ANSI colors for the game output. Coloring is global and off by default, so that the
+//! output stays plain unless `main` turns it on
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the output should be colored
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// When to color the output, as chosen with `--color`
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ColorMode {
+    /// Only when writing to a terminal
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    /// Tries to parse a string to a color mode, like `"always"` to `ColorMode::Always`
+    pub fn from_string(s: &str) -> Option<ColorMode> {
+        match s {
+            "auto" => Some(ColorMode::Auto),
+            "always" => Some(ColorMode::Always),
+            "never" => Some(ColorMode::Never),
+            _ => None,
+        }
+    }
+}
+
+/// Turns coloring on or off
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Wraps `s` in the escape codes for the given SGR color, if coloring is on
+fn paint(code: u8, s: &str) -> String {
+    if ENABLED.load(Ordering::Relaxed) {
+        format!("\x1b[{}m{}\x1b[0m", code, s)
+    } else {
+        s.to_string()
+    }
+}
+
+/// Colors a room description or name
+pub fn room(s: &str) -> String {
+    paint(36, s)
+}
+
+/// Colors the name of an object
+pub fn object(s: &str) -> String {
+    paint(33, s)
+}
+
+/// Colors an exit direction
+pub fn direction(s: &str) -> String {
+    paint(32, s)
+}
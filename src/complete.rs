@@ -0,0 +1,177 @@
+//! Completing the line the player is typing, for a line editor to offer on the tab key. The first
+//! word is completed with the words issuing commands, and the words after it with what the command
+//! takes: directions, objects from the floor or the inventory, and so on. Completion ignores case,
+//! and only offers what the command would accept.
+
+use crate::{find_command, Command, Game, Object, DIRECTION_MAPPING, PRICES};
+
+/// The words that can replace the last word of `line`, in alphabetical order. A line ending with a
+/// space is completed with the words that can follow it
+pub fn candidates(game: &Game, line: &str) -> Vec<String> {
+    let mut words = line.split_whitespace().collect::<Vec<&str>>();
+    if line.is_empty() || line.ends_with(char::is_whitespace) {
+        words.push("");
+    }
+    let prefix = words[words.len() - 1].to_lowercase();
+
+    let options = if words.len() == 1 {
+        aliases(game)
+    } else {
+        match find_command(&words[0].to_lowercase(), &game.command_aliases) {
+            Some(Command::Teleport) if words.len() == 2 && game.debug => {
+                return named_rooms(game, &prefix)
+            }
+            Some(command) => arguments(game, command, words.len() - 1),
+            None => vec![],
+        }
+    };
+
+    let mut candidates = options
+        .into_iter()
+        .filter(|option| option.starts_with(&prefix))
+        .collect::<Vec<String>>();
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
+/// Every word issuing a command
+fn aliases(game: &Game) -> Vec<String> {
+    game.command_aliases
+        .iter()
+        .flat_map(|(aliases, _)| aliases.iter().cloned())
+        .collect()
+}
+
+/// The words for the directions, in the order of `DIRECTION_MAPPING`
+fn directions() -> Vec<String> {
+    DIRECTION_MAPPING
+        .iter()
+        .map(|(_, direction)| direction.name().to_string())
+        .collect()
+}
+
+/// What the argument at `position`, counted from 1, of the command can be
+fn arguments(game: &Game, command: Command, position: usize) -> Vec<String> {
+    let player = &game.player;
+    let floor = game
+        .dungeon
+        .rooms
+        .get(&player.location)
+        .map(|room| names(room.objects.iter().map(|(object, _)| object)))
+        .unwrap_or_default();
+    let inventory = names(player.inventory.iter().map(|(object, _)| object));
+
+    match command {
+        // Several directions can be dug in at once
+        Command::Dig => directions(),
+        Command::Fill | Command::Go if position == 1 => directions(),
+        Command::Climb if position == 1 => vec!["up".to_string(), "down".to_string()],
+        Command::Seal if position == 1 => vec!["on".to_string(), "off".to_string()],
+        Command::Take => [floor, vec!["all".to_string()]].concat(),
+        Command::Drop => [inventory, vec!["all".to_string()]].concat(),
+        Command::Equip | Command::Count | Command::Eat | Command::Sell if position == 1 => {
+            inventory
+        }
+        Command::Combine => inventory,
+        Command::Buy if position == 1 => names(PRICES.iter().map(|(object, _, _)| *object)),
+        Command::Help | Command::Alias if position == 1 => aliases(game),
+        Command::Play if position == 1 => game.macros.keys().cloned().collect(),
+        _ => vec![],
+    }
+}
+
+/// The coordinates to teleport to the rooms whose name starts with `prefix`, sorted by name.
+/// Teleporting only takes coordinates, which the names of the rooms complete to
+fn named_rooms(game: &Game, prefix: &str) -> Vec<String> {
+    let mut rooms = game
+        .dungeon
+        .rooms
+        .iter()
+        .filter_map(|(location, room)| Some((room.name.as_ref()?.to_lowercase(), location)))
+        .filter(|(name, _)| name.starts_with(prefix))
+        .collect::<Vec<_>>();
+    rooms.sort();
+
+    rooms
+        .into_iter()
+        .map(|(_, location)| format!("{} {} {}", location.0, location.1, location.2))
+        .collect()
+}
+
+/// The words used to refer to the given objects
+fn names(objects: impl Iterator<Item = Object>) -> Vec<String> {
+    objects.map(|object| object.name().to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Options;
+
+    fn game(flags: &[&str]) -> Game {
+        Game::new(&Options::from_args(flags.iter().map(|flag| flag.to_string())).unwrap())
+    }
+
+    #[test]
+    fn the_first_word_completes_to_commands() {
+        let game = game(&[]);
+
+        assert_eq!(candidates(&game, "inv"), vec!["inventory"]);
+        assert_eq!(candidates(&game, "INV"), vec!["inventory"]);
+        assert_eq!(
+            candidates(&game, "re"),
+            vec!["record", "rename", "repair", "rest", "restart"]
+        );
+        assert!(candidates(&game, "").contains(&"dig".to_string()));
+        assert!(candidates(&game, "xyz").is_empty());
+    }
+
+    #[test]
+    fn directions_complete_after_dig() {
+        let game = game(&[]);
+
+        assert_eq!(
+            candidates(&game, "dig "),
+            vec!["down", "east", "north", "south", "up", "west"]
+        );
+        assert_eq!(candidates(&game, "dig Nor"), vec!["north"]);
+        assert_eq!(candidates(&game, "dig north s"), vec!["south"]);
+    }
+
+    #[test]
+    fn objects_complete_from_where_they_can_be_taken() {
+        let game = game(&["--seed", "1"]);
+
+        assert_eq!(candidates(&game, "take "), vec!["all", "ladder", "sledge"]);
+        assert_eq!(candidates(&game, "take 2 l"), vec!["ladder"]);
+        assert_eq!(candidates(&game, "drop "), vec!["all", "sledge"]);
+        assert_eq!(candidates(&game, "equip s"), vec!["sledge"]);
+        assert!(candidates(&game, "equip sledge ").is_empty());
+    }
+
+    #[test]
+    fn commands_without_arguments_complete_to_nothing() {
+        let game = game(&[]);
+
+        assert!(candidates(&game, "look ").is_empty());
+        assert!(candidates(&game, "xyz ").is_empty());
+    }
+
+    #[test]
+    fn room_names_complete_to_coordinates_when_teleporting() {
+        let mut game = game(&["--debug"]);
+        game.step("name Entrance");
+
+        assert_eq!(candidates(&game, "goto ent"), vec!["0 0 0"]);
+        assert!(candidates(&game, "goto x").is_empty());
+    }
+
+    #[test]
+    fn teleporting_completes_to_nothing_without_debug() {
+        let mut game = game(&[]);
+        game.step("name Entrance");
+
+        assert!(candidates(&game, "goto ").is_empty());
+    }
+}
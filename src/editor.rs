@@ -0,0 +1,174 @@
+//! A small line editor for playing on a terminal, completing the word being typed when tab is
+//! pressed with what `Game::completions` offers. It takes the terminal out of line mode with `stty`
+//! for as long as it lives, so it only works where `stty` does; elsewhere lines are read as typed.
+
+use rcrpg_rust::{Game, LineSource};
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+
+/// Reads lines from the terminal of the standard input one key at a time
+pub struct Editor {
+    /// How the terminal was set up before, as printed by `stty -g`, to set it back when done
+    saved: String,
+}
+
+impl Editor {
+    /// Takes over the terminal, unless its settings can't be changed. Ctrl-C then ends the input
+    /// like Ctrl-D instead of killing the game, so that the terminal always gets set back
+    pub fn new() -> Option<Editor> {
+        let saved = stty(&["-g"])?.trim().to_string();
+        stty(&["-icanon", "-echo", "-isig", "min", "1"])?;
+
+        Some(Editor { saved })
+    }
+}
+
+impl Drop for Editor {
+    fn drop(&mut self) {
+        stty(&[&self.saved]);
+    }
+}
+
+impl LineSource for Editor {
+    fn read_line(&mut self, game: &Game, line: &mut String) -> io::Result<usize> {
+        let mut stdout = io::stdout();
+        let mut typed = String::new();
+        // Bytes of a character typed but not complete yet
+        let mut pending = vec![];
+
+        for byte in io::stdin().lock().bytes() {
+            match byte? {
+                b'\n' | b'\r' => {
+                    writeln!(stdout)?;
+                    typed.push('\n');
+                    line.push_str(&typed);
+                    return Ok(typed.len());
+                }
+                // Ctrl-D on an empty line, or Ctrl-C
+                0x04 if typed.is_empty() => break,
+                0x03 => break,
+                0x7f | 0x08 => {
+                    if typed.pop().is_some() {
+                        write!(stdout, "\x08 \x08")?;
+                    }
+                }
+                b'\t' => {
+                    let (completed, choices) = complete(&typed, &game.completions(&typed));
+                    if !choices.is_empty() {
+                        write!(stdout, "\n{}\n", choices.join("  "))?;
+                    }
+                    typed = completed;
+                    write!(stdout, "\r\x1b[K{}{}", game.prompt(), typed)?;
+                }
+                byte if byte < 0x20 => {}
+                byte => {
+                    pending.push(byte);
+                    if let Ok(character) = std::str::from_utf8(&pending) {
+                        write!(stdout, "{}", character)?;
+                        typed.push_str(character);
+                        pending.clear();
+                    }
+                }
+            }
+            stdout.flush()?;
+        }
+
+        writeln!(stdout)?;
+        Ok(0)
+    }
+}
+
+/// Runs `stty` on the terminal of the standard input, returning what it prints if it worked
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()
+        .ok()?;
+
+    if output.status.success() {
+        String::from_utf8(output.stdout).ok()
+    } else {
+        None
+    }
+}
+
+/// What pressing tab does to the `line` being typed, given the `candidates` for its last word: the
+/// line with the last word completed as far as all the candidates agree, and the candidates to show
+/// when that adds nothing
+fn complete(line: &str, candidates: &[String]) -> (String, Vec<String>) {
+    let start = line
+        .char_indices()
+        .filter(|(_, character)| character.is_whitespace())
+        .map(|(index, character)| index + character.len_utf8())
+        .next_back()
+        .unwrap_or(0);
+    let (before, word) = line.split_at(start);
+
+    match candidates {
+        [] => (line.to_string(), vec![]),
+        [candidate] => (format!("{}{} ", before, candidate), vec![]),
+        _ => {
+            let common =
+                candidates
+                    .iter()
+                    .skip(1)
+                    .fold(candidates[0].clone(), |common, candidate| {
+                        common
+                            .chars()
+                            .zip(candidate.chars())
+                            .take_while(|(a, b)| a == b)
+                            .map(|(a, _)| a)
+                            .collect()
+                    });
+
+            if common.chars().count() > word.chars().count() {
+                (format!("{}{}", before, common), vec![])
+            } else {
+                (line.to_string(), candidates.to_vec())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn a_single_candidate_completes_the_word() {
+        assert_eq!(
+            complete("INV", &words(&["inventory"])),
+            ("inventory ".to_string(), vec![])
+        );
+        assert_eq!(
+            complete("take sl", &words(&["sledge"])),
+            ("take sledge ".to_string(), vec![])
+        );
+        assert_eq!(
+            complete("dig ", &words(&["down"])),
+            ("dig down ".to_string(), vec![])
+        );
+    }
+
+    #[test]
+    fn several_candidates_complete_as_far_as_they_agree() {
+        assert_eq!(
+            complete("r", &words(&["record", "rest", "restart"])),
+            ("re".to_string(), vec![])
+        );
+        assert_eq!(
+            complete("re", &words(&["record", "rest", "restart"])),
+            ("re".to_string(), words(&["record", "rest", "restart"]))
+        );
+    }
+
+    #[test]
+    fn no_candidate_leaves_the_line_alone() {
+        assert_eq!(complete("xyz", &[]), ("xyz".to_string(), vec![]));
+    }
+}
@@ -0,0 +1,264 @@
+//! Reading hand-authored dungeons from layout files. Layouts use a small subset of TOML: each room
+//! is a `[[room]]` table with a `location`, and optionally a `name`, a `description` and a list of
+//! `objects`, where an object repeated several times is stacked. Passages can be `locked` by
+//! listing their directions in either of the rooms they join, and a room can hold a `forge` or a
+//! `vendor`. The prize rooms are listed as `goals` before the first room, and are at
+//! `DEFAULT_GOAL_LOCATION` if the layout doesn't say:
+//!
+//! ```toml
+//! goals = [[1, 0, 0]]
+//!
+//! # The first room, where the player starts
+//! [[room]]
+//! location = [0, 0, 0]
+//! name = "Entrance"
+//! description = "A narrow hall."
+//! objects = ["sledge", "gold", "gold", "key"]
+//! locked = ["east"]
+//! forge = true
+//! vendor = false
+//! ```
+
+use crate::objects::CustomObjects;
+use crate::{Direction, Dungeon, Location, Object, Room, DEFAULT_GOAL_LOCATION};
+use std::collections::HashMap;
+
+/// A value on the right side of a `key = value` line
+pub(crate) enum Value {
+    Boolean(bool),
+    Integer(i32),
+    Text(String),
+    List(Vec<Value>),
+}
+
+/// A room as read from the layout, before it is checked
+#[derive(Default)]
+struct RoomEntry {
+    /// Line of the `[[room]]` header, for error messages
+    line: usize,
+    location: Option<Location>,
+    name: Option<String>,
+    description: Option<String>,
+    objects: Vec<Object>,
+    locked: Vec<Direction>,
+    forge: bool,
+    vendor: bool,
+}
+
+/// Builds a dungeon out of the text of a layout file, whose rooms may hold the `custom`
+/// objects. Errors tell the line they were found on
+pub fn parse(text: &str, custom: &CustomObjects) -> Result<Dungeon, String> {
+    let mut entries: Vec<RoomEntry> = vec![];
+    let mut goals = vec![];
+
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line == "[[room]]" {
+            entries.push(RoomEntry {
+                line: number,
+                ..RoomEntry::default()
+            });
+            continue;
+        }
+
+        let error = |message: &str| format!("line {}: {}", number, message);
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| error("expected KEY = VALUE"))?;
+        let (value, rest) = parse_value(value.trim()).map_err(|e| error(&e))?;
+
+        if !rest.trim().is_empty() && !rest.trim().starts_with('#') {
+            return Err(error("unexpected text after the value"));
+        }
+
+        let entry = match entries.last_mut() {
+            Some(entry) => entry,
+            None => match (key.trim(), value) {
+                ("goals", Value::List(locations)) => {
+                    for location in locations {
+                        goals.push(as_location(&location).ok_or_else(|| {
+                            error("goals are a list of locations, like [[1, 1, 5]]")
+                        })?);
+                    }
+                    continue;
+                }
+                ("goals", _) => return Err(error("wrong type of value for \"goals\"")),
+                _ => return Err(error("expected a [[room]] header first")),
+            },
+        };
+
+        match (key.trim(), value) {
+            ("location", location) => {
+                entry.location = Some(
+                    as_location(&location)
+                        .ok_or_else(|| error("a location is a list of three integers"))?,
+                )
+            }
+            ("name", Value::Text(name)) => entry.name = Some(name),
+            ("description", Value::Text(description)) => entry.description = Some(description),
+            ("objects", Value::List(objects)) => {
+                for object in objects {
+                    match object {
+                        Value::Text(name) => entry.objects.push(
+                            Object::from_string(&name, custom)
+                                .ok_or_else(|| error(&format!("unknown object \"{}\"", name)))?,
+                        ),
+                        _ => return Err(error("objects are a list of object names")),
+                    }
+                }
+            }
+            ("locked", Value::List(directions)) => {
+                for direction in directions {
+                    match direction {
+                        Value::Text(name) => entry
+                            .locked
+                            .push(Direction::from_string(&name).ok_or_else(|| {
+                                error(&format!("unknown direction \"{}\"", name))
+                            })?),
+                        _ => return Err(error("locked passages are a list of directions")),
+                    }
+                }
+            }
+            ("forge", Value::Boolean(forge)) => entry.forge = forge,
+            ("vendor", Value::Boolean(vendor)) => entry.vendor = vendor,
+            ("name", _)
+            | ("description", _)
+            | ("objects", _)
+            | ("locked", _)
+            | ("forge", _)
+            | ("vendor", _) => {
+                return Err(error(&format!(
+                    "wrong type of value for \"{}\"",
+                    key.trim()
+                )))
+            }
+            (key, _) => return Err(error(&format!("unknown key \"{}\"", key))),
+        }
+    }
+
+    let mut rooms = HashMap::new();
+    let mut locks = vec![];
+    for entry in entries {
+        let location = entry
+            .location
+            .ok_or_else(|| format!("line {}: the room has no location", entry.line))?;
+
+        let mut room = Room::new().with_objects(entry.objects);
+        room.name = entry.name;
+        room.description = entry.description;
+        room.forge = entry.forge;
+        room.vendor = entry.vendor;
+        let line = entry.line;
+        locks.extend(
+            entry
+                .locked
+                .into_iter()
+                .map(|direction| (line, location, direction)),
+        );
+
+        if rooms.insert(location, room).is_some() {
+            return Err(format!(
+                "line {}: there is already a room at {:?}",
+                entry.line, location
+            ));
+        }
+    }
+
+    if !rooms.contains_key(&Location(0, 0, 0)) {
+        return Err("there is no room at (0, 0, 0), where the player starts".to_string());
+    }
+
+    let mut dungeon = Dungeon {
+        rooms,
+        goals: if goals.is_empty() {
+            vec![DEFAULT_GOAL_LOCATION]
+        } else {
+            goals
+        },
+        others: vec![],
+    };
+    for (line, location, direction) in locks {
+        if !dungeon.lock(location, direction) {
+            return Err(format!(
+                "line {}: there is no room {}ward to lock the passage to",
+                line, direction
+            ));
+        }
+    }
+
+    Ok(dungeon)
+}
+
+/// The location a value stands for, if it is a list of three integers
+pub(crate) fn as_location(value: &Value) -> Option<Location> {
+    match value {
+        Value::List(coordinates) => match coordinates.as_slice() {
+            [Value::Integer(x), Value::Integer(y), Value::Integer(z)] => Some(Location(*x, *y, *z)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Parses the value at the start of `s`, returning it along with the text following it
+pub(crate) fn parse_value(s: &str) -> Result<(Value, &str), String> {
+    if let Some(rest) = s.strip_prefix('"') {
+        let mut text = String::new();
+        let mut chars = rest.char_indices();
+
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '"' => return Ok((Value::Text(text), &rest[index + 1..])),
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => text.push('\n'),
+                    Some((_, '"')) => text.push('"'),
+                    Some((_, '\\')) => text.push('\\'),
+                    _ => return Err("invalid escape sequence".to_string()),
+                },
+                c => text.push(c),
+            }
+        }
+
+        Err("unterminated string".to_string())
+    } else if let Some(mut rest) = s.strip_prefix('[') {
+        let mut values = vec![];
+
+        loop {
+            rest = rest.trim_start();
+
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((Value::List(values), after));
+            }
+
+            let (value, after) = parse_value(rest)?;
+            values.push(value);
+            rest = after.trim_start();
+
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after;
+            } else if !rest.starts_with(']') {
+                return Err("expected , or ] in the list".to_string());
+            }
+        }
+    } else if let Some(rest) = s.strip_prefix("true") {
+        Ok((Value::Boolean(true), rest))
+    } else if let Some(rest) = s.strip_prefix("false") {
+        Ok((Value::Boolean(false), rest))
+    } else {
+        let end = s
+            .find(|c: char| !(c == '-' || c.is_ascii_digit()))
+            .unwrap_or(s.len());
+
+        s[..end]
+            .parse()
+            .map(|integer| (Value::Integer(integer), &s[end..]))
+            .map_err(|_| "expected a string, an integer, a boolean or a list".to_string())
+    }
+}
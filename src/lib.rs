@@ -0,0 +1,6049 @@
+//! Implementation of the simple text-based game [RCRPG](https://web.archive.org/web/20080212201605/http://shortcircuit.us/muddy-kinda-like-a-mud-but-single-player/)
+//! in Rust
+//!
+//! The game is driven through `Game`: build one out of some `Options`, then feed it command lines
+//! with `Game::step`, which returns the text to show for each, or let `Game::run` play a whole
+//! game out of a reader.
+
+pub mod color;
+mod complete;
+mod layout;
+mod messages;
+mod objects;
+mod save;
+pub mod scores;
+pub mod server;
+
+pub use color::ColorMode;
+pub use messages::{message, message_with, Messages};
+use objects::{CustomObject, CustomObjects};
+use rand::prelude::*;
+use std::borrow::BorrowMut;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt::{Debug, Display};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, Write};
+use std::iter::FromIterator;
+use std::ops::Add;
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{fmt, io};
+
+/// Maps each Locations to a direction
+const DIRECTION_MAPPING: [(Location, Direction); 6] = [
+    (Location(0, -1, 0), Direction::North),
+    (Location(0, 1, 0), Direction::South),
+    (Location(-1, 0, 0), Direction::West),
+    (Location(1, 0, 0), Direction::East),
+    (Location(0, 0, 1), Direction::Down),
+    (Location(0, 0, -1), Direction::Up),
+];
+
+/// Maximum number of edits between a mistyped command and an alias for it to be suggested
+const SUGGESTION_DISTANCE: usize = 2;
+
+/// Depth to reach for the `DeepDown` achievement
+const ACHIEVEMENT_DEPTH: i32 = 5;
+
+/// Moves to make for the `LongWalk` achievement
+const ACHIEVEMENT_MOVES: u32 = 100;
+
+/// How many commands can be undone in a row
+const UNDO_HISTORY_SIZE: usize = 20;
+
+/// Most rooms a map spans along each axis; the parts of bigger dungeons far from the player are
+/// left out
+const MAP_SPAN: u32 = 64;
+
+/// Chance that a freshly dug room hides a trap, before the biome makes it likelier
+const HAZARD_PROBABILITY: f32 = 0.1;
+
+/// Chance that a freshly dug room hides an object
+const HIDDEN_OBJECT_PROBABILITY: f32 = 0.2;
+
+/// Chance that searching a room uncovers one of its hidden objects
+const SEARCH_PROBABILITY: f32 = 0.5;
+
+/// Health lost when springing a trap
+const HAZARD_DAMAGE: i32 = 3;
+
+/// Health lost when a tunnel collapses while digging
+const COLLAPSE_DAMAGE: i32 = 1;
+
+/// Health recovered by resting once
+const REST_HEALTH: i32 = 2;
+
+/// Chance that something wanders by and hurts the player while they rest
+const REST_HAZARD_PROBABILITY: f32 = 0.1;
+
+/// Hunger at which the player's stomach starts growling
+const HUNGRY: u32 = 20;
+
+/// Hunger at which the player starves, losing health at every move
+const STARVING: u32 = 30;
+
+/// Health lost at every move while starving
+const STARVATION_DAMAGE: i32 = 1;
+
+/// Hunger taken away by eating once
+const FOOD_NOURISHMENT: u32 = 15;
+
+/// Chance that a room dug downwards turns out to be a pit
+const PIT_PROBABILITY: f32 = 0.25;
+
+/// Points scored for each piece of gold carried out of the dungeon
+const GOLD_SCORE: u32 = 10;
+
+/// Points scored for each point of health left at the end
+const HEALTH_SCORE: u32 = 5;
+
+/// Location of the room holding the prize, unless chosen otherwise with `--goal`
+const DEFAULT_GOAL_LOCATION: Location = Location(1, 1, 5);
+
+/// Pieces of gold lying in each prize room
+const PRIZE_GOLD: u32 = 10;
+
+/// How many rooms a sledge can dig before breaking in hard games
+const HARD_SLEDGE_DURABILITY: u32 = 10;
+
+/// Gold the smith asks to repair a sledge
+const REPAIR_COST: u32 = 3;
+
+/// Gold the smith asks to forge a new sledge
+const FORGE_COST: u32 = 5;
+
+/// What the merchant asks for each object, and the lower price they pay for it
+const PRICES: [(Object, u32, u32); 5] = [
+    (Object::Ladder, 4, 2),
+    (Object::Sledge, 6, 3),
+    (Object::Rope, 3, 1),
+    (Object::Key, 8, 4),
+    (Object::Food, 2, 1),
+];
+
+/// What can be made with `combine`: the objects used up, in their listing order, and the one made
+/// out of them
+const RECIPES: [(&[Object], Object); 2] = [
+    (&[Object::Rope, Object::Rope], Object::Ladder),
+    (
+        &[
+            Object::Gold,
+            Object::Gold,
+            Object::Gold,
+            Object::Gold,
+            Object::Gold,
+        ],
+        Object::Key,
+    ),
+];
+
+/// Maps the (possibly user-defined) aliases to their actual action, so that for instance a player
+/// can input either `n` or `north` to go North, and can also define new aliases
+type CommandAliases = Vec<(HashSet<String>, Command)>;
+
+/// Snapshots of the dungeon and the player taken before each command that changes them, the most
+/// recent last
+type History = VecDeque<(Dungeon, Player)>;
+
+/// 3D coordinates of objects in the dungeon
+#[derive(Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
+struct Location(i32, i32, i32);
+
+impl FromStr for Location {
+    type Err = String;
+
+    /// Parses coordinates written as `X,Y,Z`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let coordinates = s
+            .split(',')
+            .map(|c| c.trim().parse::<i32>())
+            .collect::<Result<Vec<i32>, _>>();
+
+        match coordinates.as_deref() {
+            Ok([x, y, z]) => Ok(Location(*x, *y, *z)),
+            _ => Err(format!("\"{}\" is not a location like X,Y,Z", s)),
+        }
+    }
+}
+
+impl Add for Location {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Location(self.0 + rhs.0, self.1 + rhs.1, self.2 + rhs.2)
+    }
+}
+
+impl Location {
+    /// Sum of two locations, unless a coordinate would overflow and wrap around to the other end
+    /// of the world
+    fn checked_add(self, rhs: Location) -> Option<Location> {
+        Some(Location(
+            self.0.checked_add(rhs.0)?,
+            self.1.checked_add(rhs.1)?,
+            self.2.checked_add(rhs.2)?,
+        ))
+    }
+
+    /// Number of rooms to cross to get from `self` to `other` moving only along the axes, at most
+    /// `i32::MAX` for locations at opposite ends of the world
+    fn manhattan_distance(self, other: Location) -> i32 {
+        self.exact_distance(other).min(i32::MAX as u64) as i32
+    }
+
+    /// Number of rooms to cross to get from `self` to `other` moving only along the axes, wide
+    /// enough to hold the distance between any two locations
+    fn exact_distance(self, other: Location) -> u64 {
+        self.0.abs_diff(other.0) as u64
+            + self.1.abs_diff(other.1) as u64
+            + self.2.abs_diff(other.2) as u64
+    }
+
+    /// Directions in which a step brings `self` closer to `other`, in the order of
+    /// `DIRECTION_MAPPING`
+    fn directions_towards(self, other: Location) -> Vec<Direction> {
+        DIRECTION_MAPPING
+            .iter()
+            .filter(|d| {
+                self.checked_add(d.0)
+                    .is_some_and(|next| next.exact_distance(other) < self.exact_distance(other))
+            })
+            .map(|d| d.1)
+            .collect()
+    }
+
+    /// The direction leading from `self` to `other`, if they are next to each other
+    fn direction_to(self, other: Location) -> Option<Direction> {
+        DIRECTION_MAPPING
+            .iter()
+            .find(|d| self.checked_add(d.0) == Some(other))
+            .map(|d| d.1)
+    }
+}
+
+impl Debug for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "({}, {}, {})", self.0, self.1, self.2)
+    }
+}
+
+/// Objects that can be found in the dungon rooms. They are always listed by category, then
+/// alphabetically
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
+enum Object {
+    Ladder,
+    Sledge,
+    Gold,
+    Rope,
+    /// Opens a locked passage, and is used up doing so
+    Key,
+    /// Rations that take hunger away, used up when eaten
+    Food,
+    /// A kind of object read from an object file
+    Custom(&'static CustomObject),
+}
+
+/// Kinds of objects, in the order they are listed
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug)]
+pub enum Category {
+    /// Kept and used over and over
+    Tool,
+    /// Used up when used
+    Consumable,
+    /// Only good to trade or hoard
+    Treasure,
+}
+
+impl Category {
+    /// Tries to parse a string to a category, like `"tool"` to `Category::Tool`
+    fn from_string(s: &str) -> Option<Category> {
+        match s {
+            "tool" => Some(Category::Tool),
+            "consumable" => Some(Category::Consumable),
+            "treasure" => Some(Category::Treasure),
+            _ => None,
+        }
+    }
+}
+
+impl Ord for Object {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.category(), self.name()).cmp(&(other.category(), other.name()))
+    }
+}
+
+impl PartialOrd for Object {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Object::Ladder => write!(f, "{}", color::object(&message("object.ladder"))),
+            Object::Sledge => write!(f, "{}", color::object(&message("object.sledge"))),
+            Object::Gold => write!(f, "{}", color::object(&message("object.gold"))),
+            Object::Rope => write!(f, "{}", color::object(&message("object.rope"))),
+            Object::Key => write!(f, "{}", color::object(&message("object.key"))),
+            Object::Food => write!(f, "{}", color::object(&message("object.food"))),
+            Object::Custom(object) => write!(f, "{}", color::object(&object.description)),
+        }
+    }
+}
+
+impl Object {
+    /// Tries to parse a string to an object, like `"gold"` to `Object::Gold`, looking among the
+    /// `custom` objects too
+    fn from_string(s: &str, custom: &CustomObjects) -> Option<Object> {
+        match s {
+            "ladder" => Some(Object::Ladder),
+            "sledge" => Some(Object::Sledge),
+            "gold" => Some(Object::Gold),
+            "rope" => Some(Object::Rope),
+            "key" => Some(Object::Key),
+            "food" => Some(Object::Food),
+            _ => custom.find(s).map(Object::Custom),
+        }
+    }
+
+    /// The word used to refer to the object in commands, like `"gold"` for `Object::Gold`
+    fn name(self) -> &'static str {
+        match self {
+            Object::Ladder => "ladder",
+            Object::Sledge => "sledge",
+            Object::Gold => "gold",
+            Object::Rope => "rope",
+            Object::Key => "key",
+            Object::Food => "food",
+            Object::Custom(object) => &object.name,
+        }
+    }
+
+    /// The kind of object, deciding where it is listed
+    fn category(self) -> Category {
+        match self {
+            Object::Ladder | Object::Sledge | Object::Rope => Category::Tool,
+            Object::Key | Object::Food => Category::Consumable,
+            Object::Custom(object) => object.category,
+            Object::Gold => Category::Treasure,
+        }
+    }
+
+    /// Whether the object, when equipped, can dig new rooms
+    fn can_dig(self) -> bool {
+        match self {
+            Object::Sledge => true,
+            Object::Ladder | Object::Gold | Object::Rope | Object::Key | Object::Food => false,
+            Object::Custom(object) => object.digs,
+        }
+    }
+
+    /// Whether carrying the object lets the player climb up to the room above
+    fn can_climb(self) -> bool {
+        match self {
+            Object::Ladder => true,
+            Object::Sledge | Object::Gold | Object::Rope | Object::Key | Object::Food => false,
+            Object::Custom(object) => object.climbs,
+        }
+    }
+
+    /// How heavy a single object of this kind is
+    fn weight(self) -> u32 {
+        match self {
+            Object::Ladder => 6,
+            Object::Sledge => 5,
+            Object::Rope => 2,
+            Object::Gold | Object::Key | Object::Food => 1,
+            Object::Custom(object) => object.weight,
+        }
+    }
+
+    /// Describes a number of objects of this kind, like `a ladder`, `2 ladders`, `some gold`,
+    /// `3 gold` or `3 rations`
+    fn display_count(self, count: u32) -> String {
+        match (count, self) {
+            (1, _) => self.to_string(),
+            (_, Object::Gold) => color::object(&message_with("objects.gold", &[("count", &count)])),
+            (_, Object::Food) => color::object(&message_with("objects.food", &[("count", &count)])),
+            _ => color::object(&message_with(
+                "objects.other",
+                &[("count", &count), ("name", &self.name())],
+            )),
+        }
+    }
+}
+
+/// A bag of objects, keeping track of how many of each kind it holds
+#[derive(Clone, Default)]
+struct Inventory(BTreeMap<Object, u32>);
+
+impl Inventory {
+    /// How many objects of the given kind the inventory holds
+    fn count(&self, object: Object) -> u32 {
+        self.0.get(&object).copied().unwrap_or(0)
+    }
+
+    fn contains(&self, object: Object) -> bool {
+        self.count(object) > 0
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Adds `count` objects of the given kind
+    fn add(&mut self, object: Object, count: u32) {
+        if count > 0 {
+            *self.0.entry(object).or_insert(0) += count;
+        }
+    }
+
+    /// Removes up to `count` objects of the given kind, returning how many were actually removed
+    fn remove(&mut self, object: Object, count: u32) -> u32 {
+        let available = self.count(object);
+        let removed = available.min(count);
+
+        if removed == available {
+            self.0.remove(&object);
+        } else {
+            self.0.insert(object, available - removed);
+        }
+
+        removed
+    }
+
+    /// Moves all the objects of `other` into this inventory
+    fn append(&mut self, other: &mut Inventory) {
+        for (object, count) in std::mem::take(&mut other.0) {
+            self.add(object, count);
+        }
+    }
+
+    /// The kinds of objects in the inventory along with their count
+    fn iter(&self) -> impl Iterator<Item = (Object, u32)> + '_ {
+        self.0.iter().map(|(object, count)| (*object, *count))
+    }
+
+    /// Whether any of the objects in the inventory lets the player climb up
+    fn can_climb(&self) -> bool {
+        self.iter().any(|(object, _)| object.can_climb())
+    }
+
+    /// The total weight of the objects in the inventory
+    fn weight(&self) -> u32 {
+        self.iter()
+            .map(|(object, count)| object.weight() * count)
+            .sum()
+    }
+
+    /// Comma separated list of the objects in the inventory
+    fn list(&self) -> String {
+        self.iter()
+            .map(|(object, count)| object.display_count(count))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+impl Extend<Object> for Inventory {
+    fn extend<T: IntoIterator<Item = Object>>(&mut self, iter: T) {
+        for object in iter {
+            self.add(object, 1);
+        }
+    }
+}
+
+impl FromIterator<Object> for Inventory {
+    fn from_iter<T: IntoIterator<Item = Object>>(iter: T) -> Self {
+        let mut inventory = Inventory::default();
+        inventory.extend(iter);
+        inventory
+    }
+}
+
+/// Player information
+#[derive(Clone)]
+struct Player {
+    /// Room where the player currently is
+    location: Location,
+    /// The objects carried by the player
+    inventory: Inventory,
+    /// The object wieled by the player, if any
+    equipped: Option<Object>,
+    /// Rooms the player has set foot in at least once
+    visited: HashSet<Location>,
+    /// Remaining health; the player dies when it drops to zero
+    health: i32,
+    /// Health the player starts with and can never exceed
+    max_health: i32,
+    /// How many times the player walked from a room to another
+    moves: u32,
+    /// How hungry the player is, growing with every move until they eat
+    hunger: u32,
+    /// Rooms the player walked out of, the latest last, to retrace their steps with `back`
+    trail: Vec<Location>,
+    /// How many rooms the player dug
+    rooms_dug: u32,
+    /// How much gold the player picked up, counting again gold dropped and taken back
+    gold_collected: u32,
+    /// Prize rooms the player took gold from
+    collected: HashSet<Location>,
+    /// How many rooms a sledge can dig before breaking, if it ever breaks
+    sledge_durability: Option<u32>,
+    /// How many rooms the sledge in use can still dig, when sledges break
+    sledge_uses_left: u32,
+    /// Direction of the last move, to keep going that way with a bare `go`
+    facing: Option<Direction>,
+    /// Name picked with `rename`, shown to the other players sharing the dungeon
+    name: Option<String>,
+}
+
+impl Player {
+    /// A player in the first room, carrying the given objects and in full health
+    fn new(max_health: i32, inventory: Inventory, sledge_durability: Option<u32>) -> Self {
+        Player {
+            location: Location(0, 0, 0),
+            inventory,
+            equipped: None,
+            visited: HashSet::from_iter(vec![Location(0, 0, 0)]),
+            health: max_health,
+            max_health,
+            moves: 0,
+            hunger: 0,
+            trail: vec![],
+            rooms_dug: 0,
+            gold_collected: 0,
+            collected: HashSet::new(),
+            sledge_durability,
+            sledge_uses_left: sledge_durability.unwrap_or(0),
+            facing: None,
+            name: None,
+        }
+    }
+
+    /// Lowers the player's health, never below zero
+    fn damage(&mut self, amount: i32) {
+        self.health = (self.health - amount).max(0);
+    }
+
+    /// Makes the player hungrier after some moves, hurting them once they are starving. Returns
+    /// what the player feels, if anything
+    fn grow_hungry(&mut self, moves: u32) -> Option<String> {
+        if moves == 0 {
+            return None;
+        }
+
+        let before = self.hunger;
+        self.hunger += moves;
+
+        if self.hunger >= STARVING {
+            let damage = STARVATION_DAMAGE * moves as i32;
+            self.damage(damage);
+            Some(message_with(
+                "hunger.starving",
+                &[
+                    ("damage", &damage),
+                    ("health", &self.health),
+                    ("max_health", &self.max_health),
+                ],
+            ))
+        } else if before < HUNGRY && self.hunger >= HUNGRY {
+            Some(message("hunger.hungry"))
+        } else {
+            None
+        }
+    }
+
+    /// Wears the sledge down after digging. When it's worn out one sledge is taken out of the
+    /// inventory, and the next one (if any) starts fresh. Returns whether the sledge broke
+    fn wear_sledge(&mut self) -> bool {
+        let durability = match self.sledge_durability {
+            Some(durability) => durability,
+            None => return false,
+        };
+
+        self.sledge_uses_left = self.sledge_uses_left.saturating_sub(1);
+        if self.sledge_uses_left > 0 {
+            return false;
+        }
+
+        self.inventory.remove(Object::Sledge, 1);
+        self.unequip_if_missing();
+        self.sledge_uses_left = durability;
+        true
+    }
+
+    /// Keeps count of the gold picked up, and of the prize rooms it was found in
+    fn pick_up_gold(&mut self, amount: u32, in_prize_room: bool) {
+        self.gold_collected += amount;
+        if amount > 0 && in_prize_room {
+            self.collected.insert(self.location);
+        }
+    }
+
+    /// Unequips the equipped object if the player doesn't carry any of its kind anymore. Returns
+    /// whether it was unequipped
+    fn unequip_if_missing(&mut self) -> bool {
+        match self.equipped {
+            Some(object) if !self.inventory.contains(object) => {
+                self.equipped = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn is_dead(&self) -> bool {
+        self.health == 0
+    }
+}
+
+/// Information about each room of the dungeon
+#[derive(Clone)]
+struct Room {
+    /// Name given to the room by the player
+    name: Option<String>,
+    /// Fixed description for special rooms (like the first one or the prize room) or a random
+    /// flavor text for dug ones
+    description: Option<String>,
+    /// Objects currently in the room
+    objects: Inventory,
+    /// Objects hidden in the room, that only show up after searching it
+    hidden: Inventory,
+    /// Whether the room was dug by the player rather than being part of the starting dungeon
+    dug: bool,
+    /// Whether the room is a steep pit that can only be entered from above with a rope
+    pit: bool,
+    /// Whether a trap is waiting for the next one to walk in. Traps only spring once
+    hazard: bool,
+    /// Whether a smith works here, repairing and forging sledges for gold
+    forge: bool,
+    /// Whether a merchant trades objects for gold here
+    vendor: bool,
+    /// Exits that can only be walked through after unlocking them with a key. The room on the
+    /// other side has the opposite direction locked too (see `Dungeon::lock`)
+    locked: HashSet<Direction>,
+}
+
+/// The kind of place a room is in, decided by how deep it lies
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Biome {
+    /// Damp caves close to the surface
+    Caves,
+    /// Caverns glittering with crystals, from depth 3
+    CrystalCaverns,
+    /// Scorching depths lit by molten rock, from depth 6
+    MagmaDepths,
+}
+
+impl Display for Biome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            Biome::Caves => write!(f, "{}", message("biome.caves")),
+            Biome::CrystalCaverns => write!(f, "{}", message("biome.crystal-caverns")),
+            Biome::MagmaDepths => write!(f, "{}", message("biome.magma-depths")),
+        }
+    }
+}
+
+impl Biome {
+    /// The biome rooms at the given location belong to
+    fn at(location: Location) -> Biome {
+        match location.2 {
+            z if z >= 6 => Biome::MagmaDepths,
+            z if z >= 3 => Biome::CrystalCaverns,
+            _ => Biome::Caves,
+        }
+    }
+
+    /// Message ids of the flavor descriptions for the rooms dug in the biome
+    fn descriptions(self) -> &'static [&'static str] {
+        match self {
+            Biome::Caves => &[
+                "description.caves.1",
+                "description.caves.2",
+                "description.caves.3",
+                "description.caves.4",
+            ],
+            Biome::CrystalCaverns => &[
+                "description.crystal-caverns.1",
+                "description.crystal-caverns.2",
+                "description.crystal-caverns.3",
+            ],
+            Biome::MagmaDepths => &[
+                "description.magma-depths.1",
+                "description.magma-depths.2",
+                "description.magma-depths.3",
+                "description.magma-depths.4",
+            ],
+        }
+    }
+
+    /// How many times likelier than usual gold is to be found in the biome
+    fn gold_factor(self) -> f32 {
+        match self {
+            Biome::Caves => 1.0,
+            Biome::CrystalCaverns => 1.5,
+            Biome::MagmaDepths => 2.0,
+        }
+    }
+
+    /// How many times likelier than usual traps are in the biome
+    fn hazard_factor(self) -> f32 {
+        match self {
+            Biome::Caves => 1.0,
+            Biome::CrystalCaverns => 1.5,
+            Biome::MagmaDepths => 2.5,
+        }
+    }
+}
+
+impl Room {
+    fn new() -> Self {
+        Room {
+            name: None,
+            description: None,
+            objects: Inventory::default(),
+            hidden: Inventory::default(),
+            dug: false,
+            pit: false,
+            hazard: false,
+            forge: false,
+            vendor: false,
+            locked: HashSet::new(),
+        }
+    }
+
+    /// Sets the room description
+    fn with_description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Sets the objects in the room
+    fn with_objects(mut self, objects: Vec<Object>) -> Self {
+        self.objects.extend(objects);
+        self
+    }
+
+    /// Marks the room as dug by the player
+    fn dug_by_player(mut self) -> Self {
+        self.dug = true;
+        self
+    }
+
+    /// Sets whether the room is a pit
+    fn with_pit(mut self, pit: bool) -> Self {
+        self.pit = pit;
+        self
+    }
+
+    /// Adds some randoms objects to the room, each kind with the chance given by `config`, and
+    /// sometimes a trap or a hidden object, more likely of the kinds that spawn more often. Deeper
+    /// biomes hold more gold and more traps
+    fn with_random_objects<R: Rng + ?Sized>(
+        mut self,
+        rng: &mut R,
+        config: &GenerationConfig,
+        biome: Biome,
+    ) -> Self {
+        self.objects.extend(config.roll_objects(rng, biome));
+        self.hazard = rng.gen::<f32>() < HAZARD_PROBABILITY * biome.hazard_factor();
+        if rng.gen::<f32>() < HIDDEN_OBJECT_PROBABILITY {
+            if let Ok((object, _)) = config
+                .spawn_probabilities
+                .choose_weighted(rng, |(_, probability)| *probability)
+            {
+                self.hidden.add(*object, 1);
+            }
+        }
+        self
+    }
+
+    /// Gives the room a random flavor description fitting its biome
+    fn with_flavor<R: Rng + ?Sized>(mut self, rng: &mut R, biome: Biome) -> Self {
+        self.description = biome
+            .descriptions()
+            .choose(rng)
+            .map(|description| message(description));
+        self
+    }
+}
+
+/// What the player carries when the game starts
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum StartingInventory {
+    /// Just a sledge
+    Standard,
+    /// Nothing at all, a sledge has to be found first
+    Empty,
+    /// Random objects, with the same chances as in a freshly dug room
+    Random,
+    /// A sledge, a ladder and a rope
+    Generous,
+}
+
+impl StartingInventory {
+    /// Tries to parse a string to a starting inventory, like `"empty"` to
+    /// `StartingInventory::Empty`
+    fn from_string(s: &str) -> Option<StartingInventory> {
+        match s {
+            "standard" => Some(StartingInventory::Standard),
+            "empty" => Some(StartingInventory::Empty),
+            "random" => Some(StartingInventory::Random),
+            "generous" => Some(StartingInventory::Generous),
+            _ => None,
+        }
+    }
+
+    /// Fills the inventory the player starts with
+    fn roll<R: Rng + ?Sized>(self, rng: &mut R, config: &GenerationConfig) -> Inventory {
+        match self {
+            StartingInventory::Standard => Inventory::from_iter(vec![Object::Sledge]),
+            StartingInventory::Empty => Inventory::default(),
+            StartingInventory::Random => {
+                Inventory::from_iter(config.roll_objects(rng, Biome::Caves))
+            }
+            StartingInventory::Generous => {
+                Inventory::from_iter(vec![Object::Sledge, Object::Ladder, Object::Rope])
+            }
+        }
+    }
+}
+
+/// How hard the game is, bundling the spawn chances of objects, the veins of gold, what the player
+/// starts with and how long the sledge lasts
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Difficulty {
+    /// Plenty of tools and gold, and a few tools to start with
+    Easy,
+    /// The game as it is without `--difficulty`
+    Normal,
+    /// Few tools and little gold, nothing to start with and sledges that break
+    Hard,
+}
+
+impl Difficulty {
+    /// Tries to parse a string to a difficulty, like `"hard"` to `Difficulty::Hard`
+    fn from_string(s: &str) -> Option<Difficulty> {
+        match s {
+            "easy" => Some(Difficulty::Easy),
+            "normal" => Some(Difficulty::Normal),
+            "hard" => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+
+    /// Changes the options to the ones of this difficulty
+    fn apply(self, options: &mut Options) {
+        let (tools, gold, veins, start, sledge_durability) = match self {
+            Difficulty::Easy => (0.5, 0.5, 0.2, StartingInventory::Generous, None),
+            Difficulty::Normal => (0.33, 0.33, 0.1, StartingInventory::Standard, None),
+            Difficulty::Hard => (
+                0.2,
+                0.2,
+                0.05,
+                StartingInventory::Empty,
+                Some(HARD_SLEDGE_DURABILITY),
+            ),
+        };
+
+        for (object, probability) in options.generation.spawn_probabilities.iter_mut() {
+            match object {
+                Object::Sledge | Object::Ladder | Object::Rope => *probability = tools,
+                Object::Gold => *probability = gold,
+                _ => {}
+            }
+        }
+        options.generation.vein_probability = veins;
+        options.start = start;
+        options.sledge_durability = sledge_durability;
+    }
+}
+
+/// Settings for the random generation of rooms
+#[derive(Clone)]
+struct GenerationConfig {
+    /// Chance of each kind of object to be found in a freshly dug room. Rolled in order
+    spawn_probabilities: Vec<(Object, f32)>,
+    /// How many rooms the dungeon can hold at most, if limited
+    max_rooms: Option<usize>,
+    /// Chance that a tunnel caves in while digging, hurting the player and leaving no room
+    collapse_probability: f32,
+    /// Chance that digging breaks into a vein of gold
+    vein_probability: f32,
+    /// Most gold a vein holds near the surface. Every 3 levels of depth add as much again
+    vein_gold: u32,
+    /// Kinds of objects read from an object file, on top of the built-in ones
+    objects: CustomObjects,
+}
+
+impl Default for GenerationConfig {
+    fn default() -> Self {
+        GenerationConfig {
+            spawn_probabilities: vec![
+                (Object::Sledge, 0.33),
+                (Object::Ladder, 0.33),
+                (Object::Gold, 0.33),
+                (Object::Rope, 0.33),
+                (Object::Key, 0.0),
+                (Object::Food, 0.15),
+            ],
+            max_rooms: None,
+            collapse_probability: 0.0,
+            vein_probability: 0.1,
+            vein_gold: 3,
+            objects: CustomObjects::default(),
+        }
+    }
+}
+
+impl GenerationConfig {
+    /// Picks a random set of objects, each kind with its spawn chance, gold made likelier by the
+    /// biome
+    fn roll_objects<R: Rng + ?Sized>(&self, rng: &mut R, biome: Biome) -> Vec<Object> {
+        self.spawn_probabilities
+            .iter()
+            .filter(|(object, probability)| {
+                let factor = match object {
+                    Object::Gold => biome.gold_factor(),
+                    _ => 1.0,
+                };
+                rng.gen::<f32>() < probability * factor
+            })
+            .map(|(object, _)| *object)
+            .collect()
+    }
+
+    /// How much gold is found in a vein dug into at the given depth, if any. Draws the vein roll
+    /// and, if there is a vein, its size
+    fn roll_gold_vein<R: Rng + ?Sized>(&self, rng: &mut R, depth: i32) -> u32 {
+        if self.vein_gold == 0 || rng.gen::<f32>() >= self.vein_probability {
+            return 0;
+        }
+
+        rng.gen_range(1, self.vein_gold + 1) * (1 + depth.max(0) as u32 / 3)
+    }
+
+    /// Adds the kinds of objects defined in an object file, each with its own spawn chance
+    fn load_objects(&mut self, path: &str) -> Result<(), String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| format!("Cannot read the object file {}: {}", path, error))?;
+        self.objects =
+            CustomObjects::load(&text).map_err(|error| format!("{}: {}", path, error))?;
+
+        self.spawn_probabilities.extend(
+            self.objects
+                .iter()
+                .map(|object| (Object::Custom(object), object.spawn_probability)),
+        );
+        Ok(())
+    }
+
+    /// Changes the spawn chance of an object from a `OBJECT=PROBABILITY` string, like `gold=0.1`
+    fn set_spawn_probability(&mut self, setting: &str) -> Result<(), String> {
+        let invalid = || format!("Invalid spawn probability \"{}\"", setting);
+
+        let (object, probability) = setting.split_once('=').ok_or_else(invalid)?;
+        let object = Object::from_string(object, &self.objects).ok_or_else(invalid)?;
+        let probability = probability
+            .parse::<f32>()
+            .ok()
+            .filter(|p| (0.0..=1.0).contains(p))
+            .ok_or_else(invalid)?;
+
+        for entry in self.spawn_probabilities.iter_mut() {
+            if entry.0 == object {
+                entry.1 = probability;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Cardinat directions
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+enum Direction {
+    North,
+    South,
+    West,
+    East,
+    Down,
+    Up,
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl Direction {
+    /// Tries to parse a string to a direction, like `"north"` to `Direction::North`
+    fn from_string(s: &str) -> Option<Direction> {
+        match s {
+            "north" => Some(Direction::North),
+            "south" => Some(Direction::South),
+            "west" => Some(Direction::West),
+            "east" => Some(Direction::East),
+            "down" => Some(Direction::Down),
+            "up" => Some(Direction::Up),
+            _ => None,
+        }
+    }
+
+    /// Returns the normalized 3D point of the location, for instance `Direction::North` is
+    /// `(0, -1, 0)` where `(x, y, z)`
+    fn to_location(self) -> Location {
+        DIRECTION_MAPPING.iter().find(|d| d.1 == self).unwrap().0
+    }
+
+    /// The word used for the direction, like `"north"` for `Direction::North`
+    fn name(self) -> &'static str {
+        match self {
+            Direction::North => "north",
+            Direction::South => "south",
+            Direction::West => "west",
+            Direction::East => "east",
+            Direction::Down => "down",
+            Direction::Up => "up",
+        }
+    }
+
+    /// The direction pointing the other way, like `Direction::South` for `Direction::North`
+    fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+            Direction::East => Direction::West,
+            Direction::Down => Direction::Up,
+            Direction::Up => Direction::Down,
+        }
+    }
+}
+
+/// Quotes and escapes a string for JSON
+fn json_string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+/// Collection of rooms
+#[derive(Clone)]
+struct Dungeon {
+    /// The rooms that make up the dungeon
+    rooms: HashMap<Location, Room>,
+    /// Locations of the rooms holding the prize. The game is won by taking gold in all of them
+    goals: Vec<Location>,
+    /// Where the other adventurers exploring the same dungeon stand and their names, when it's
+    /// shared
+    others: Vec<(Location, String)>,
+}
+
+impl Dungeon {
+    /// The starting dungeon: the first room and, far from it, a prize room full of gold at each of
+    /// the `goals`
+    fn new(goals: &[Location]) -> Self {
+        let mut rooms = HashMap::new();
+
+        rooms.insert(
+            Location(0, 0, 0),
+            Room::new()
+                .with_description(&message("description.start"))
+                .with_objects(vec![Object::Ladder, Object::Sledge]),
+        );
+        for goal in goals {
+            let mut room = Room::new().with_description(&message("description.prize"));
+            room.objects.add(Object::Gold, PRIZE_GOLD);
+            rooms.insert(*goal, room);
+        }
+
+        Dungeon {
+            rooms,
+            goals: goals.to_vec(),
+            others: vec![],
+        }
+    }
+
+    /// A random dungeon of `size` rooms, carved by wandering from the first room in random
+    /// directions, never above it. The prize room is picked at random among the rooms at least
+    /// half as far from the start as the farthest one, and a forge and a merchant are set up in
+    /// other rooms (possibly the same one). Since every room is carved from the
+    /// previous one, all of them (and so the prize) are connected to the start, which holds a
+    /// ladder to climb back up. Carving makes no pits nor locked passages, so the prize can always
+    /// be walked to by a player carrying only what lies in the first room
+    fn generate<R: Rng + ?Sized>(rng: &mut R, config: &GenerationConfig, size: usize) -> Self {
+        let start = Location(0, 0, 0);
+        let mut dungeon = Dungeon::new(&[]);
+        let mut current = start;
+
+        while dungeon.rooms.len() < size.max(2) {
+            let (offset, _) = DIRECTION_MAPPING.choose(rng).unwrap();
+            let next = current + *offset;
+
+            if next.2 < start.2 {
+                continue;
+            }
+
+            dungeon.rooms.entry(next).or_insert_with(|| {
+                Room::new()
+                    .with_random_objects(rng, config, Biome::at(next))
+                    .with_flavor(rng, Biome::at(next))
+            });
+            current = next;
+        }
+
+        let farthest = dungeon
+            .rooms
+            .keys()
+            .map(|location| location.manhattan_distance(start))
+            .max()
+            .unwrap_or(0);
+        let mut candidates = dungeon
+            .rooms
+            .keys()
+            .filter(|location| {
+                **location != start && location.manhattan_distance(start) * 2 >= farthest
+            })
+            .copied()
+            .collect::<Vec<Location>>();
+        // Sorted so that the same random numbers always pick the same room
+        candidates.sort();
+        let goal = *candidates.choose(rng).unwrap();
+
+        let mut forge_candidates = dungeon
+            .rooms
+            .keys()
+            .filter(|location| **location != start && **location != goal)
+            .copied()
+            .collect::<Vec<Location>>();
+        forge_candidates.sort();
+        if let Some(forge) = forge_candidates.choose(rng) {
+            dungeon.rooms.get_mut(forge).unwrap().forge = true;
+        }
+        if let Some(vendor) = forge_candidates.choose(rng) {
+            dungeon.rooms.get_mut(vendor).unwrap().vendor = true;
+        }
+
+        let room = dungeon.rooms.get_mut(&goal).unwrap();
+        room.description = Some(message("description.prize"));
+        room.hazard = false;
+        room.objects.add(Object::Gold, PRIZE_GOLD);
+        dungeon.goals.push(goal);
+
+        debug_assert!(dungeon.is_connected(), "generated a disconnected dungeon");
+        dungeon
+    }
+
+    /// The closest prize room the player hasn't taken gold from yet, if any is left
+    fn next_goal(&self, player: &Player) -> Option<Location> {
+        self.goals
+            .iter()
+            .filter(|goal| !player.collected.contains(goal))
+            .min_by_key(|goal| player.location.manhattan_distance(**goal))
+            .copied()
+    }
+
+    /// Reads a hand-authored dungeon from a layout file (see the `layout` module for the format),
+    /// whose rooms may hold the given custom objects
+    fn from_file(path: &str, objects: &CustomObjects) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+
+        layout::parse(&text, objects).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Locks the passage between the room at `location` and the one next to it in `direction`, from
+    /// both sides. Returns false if there is no room on either side
+    fn lock(&mut self, location: Location, direction: Direction) -> bool {
+        let other_location = match location.checked_add(direction.to_location()) {
+            Some(other_location) => other_location,
+            None => return false,
+        };
+
+        if !self.rooms.contains_key(&location) || !self.rooms.contains_key(&other_location) {
+            return false;
+        }
+
+        self.rooms
+            .get_mut(&location)
+            .unwrap()
+            .locked
+            .insert(direction);
+        self.rooms
+            .get_mut(&other_location)
+            .unwrap()
+            .locked
+            .insert(direction.opposite());
+        true
+    }
+
+    /// Unlocks the passage leaving the room at `location` in `direction`, from both sides
+    fn unlock(&mut self, location: Location, direction: Direction) {
+        if let Some(room) = self.rooms.get_mut(&location) {
+            room.locked.remove(&direction);
+        }
+        if let Some(room) = location
+            .checked_add(direction.to_location())
+            .and_then(|other_location| self.rooms.get_mut(&other_location))
+        {
+            room.locked.remove(&direction.opposite());
+        }
+    }
+
+    /// Removes the room at `location`, along with the locks on the passages leading to it
+    fn remove_room(&mut self, location: Location) {
+        if let Some(room) = self.rooms.remove(&location) {
+            for direction in room.locked {
+                self.unlock(location, direction);
+            }
+        }
+    }
+
+    /// Shortest sequence of moves taking `player` from `from` to `to` through the existing rooms,
+    /// following the same rules as walking: going up needs a ladder, going down into a pit needs a
+    /// rope and locked passages need a key. Keys are used up when walking, but here any number of
+    /// locked passages is considered open as long as the player carries a key
+    fn path_to(&self, from: Location, to: Location, player: &Player) -> Option<Vec<Direction>> {
+        let mut previous: HashMap<Location, (Location, Direction)> = HashMap::new();
+        let mut queue = VecDeque::from(vec![from]);
+
+        while let Some(location) = queue.pop_front() {
+            if location == to {
+                let mut path = vec![];
+                let mut current = location;
+
+                while let Some((before, direction)) = previous.get(&current) {
+                    path.push(*direction);
+                    current = *before;
+                }
+                path.reverse();
+
+                return Some(path);
+            }
+
+            // Only the starting point can be somewhere there is no room
+            let room = match self.rooms.get(&location) {
+                Some(room) => room,
+                None => continue,
+            };
+
+            for direction in self.exits_for_room(location) {
+                let next = location + direction.to_location();
+                let inventory = &player.inventory;
+
+                if next == from
+                    || previous.contains_key(&next)
+                    || (direction == Direction::Up
+                        && !inventory.can_climb()
+                        && !room.objects.can_climb())
+                    || (direction == Direction::Down
+                        && self.rooms[&next].pit
+                        && !inventory.contains(Object::Rope))
+                    || (room.locked.contains(&direction) && !inventory.contains(Object::Key))
+                {
+                    continue;
+                }
+
+                previous.insert(next, (location, direction));
+                queue.push_back(next);
+            }
+        }
+
+        None
+    }
+
+    /// The rooms that can be reached from the first one through adjacent rooms, whatever the
+    /// player carries
+    fn reachable(&self) -> HashSet<Location> {
+        let start = Location(0, 0, 0);
+        if !self.rooms.contains_key(&start) {
+            return HashSet::new();
+        }
+
+        let mut reached: HashSet<Location> = HashSet::from_iter(vec![start]);
+        let mut queue = VecDeque::from(vec![start]);
+        while let Some(location) = queue.pop_front() {
+            for direction in self.exits_for_room(location) {
+                let next = location + direction.to_location();
+                if reached.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        reached
+    }
+
+    /// Whether every room can be reached from the first one through adjacent rooms, whatever the
+    /// player carries
+    fn is_connected(&self) -> bool {
+        self.reachable().len() == self.rooms.len()
+    }
+
+    /// Checks that every prize room is a room the player can walk to from the first one
+    fn check_goals(&self) -> Result<(), String> {
+        let reachable = self.reachable();
+
+        for goal in &self.goals {
+            if *goal == Location(0, 0, 0) {
+                return Err("the prize can't be in the first room".to_string());
+            }
+            if !self.rooms.contains_key(goal) {
+                return Err(format!("there is no room at {:?} for the prize", goal));
+            }
+            if !reachable.contains(goal) {
+                return Err(format!(
+                    "the prize room at {:?} can't be walked to from the first room",
+                    goal
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Given a room location, returns the list of `Direction`s that lead to other rooms
+    fn exits_for_room(&self, location: Location) -> Vec<Direction> {
+        DIRECTION_MAPPING
+            .iter()
+            .filter_map(|d| {
+                let location_to_test = location.checked_add(d.0)?;
+
+                if self.rooms.contains_key(&location_to_test) {
+                    return Some(d.1);
+                }
+                None
+            })
+            .collect()
+    }
+
+    /// Renders the dungeon as an undirected Graphviz graph with a node per room, labeled with its
+    /// name or coordinates, and an edge per pair of adjacent rooms. Rooms are listed by coordinates
+    /// so the output only depends on the layout
+    fn to_dot(&self) -> String {
+        let mut locations: Vec<&Location> = self.rooms.keys().collect();
+        locations.sort();
+
+        let node_id = |l: &Location| format!("\"{},{},{}\"", l.0, l.1, l.2);
+        let mut lines = vec!["graph dungeon {".to_string()];
+
+        for location in &locations {
+            let label = match &self.rooms[location].name {
+                Some(name) => name.replace('\\', "\\\\").replace('"', "\\\""),
+                None => format!("{:?}", location),
+            };
+            lines.push(format!("    {} [label=\"{}\"];", node_id(location), label));
+        }
+
+        for location in &locations {
+            for direction in &[Direction::South, Direction::East, Direction::Down] {
+                let neighbour = match location.checked_add(direction.to_location()) {
+                    Some(neighbour) => neighbour,
+                    None => continue,
+                };
+
+                if self.rooms.contains_key(&neighbour) {
+                    lines.push(format!(
+                        "    {} -- {} [label=\"{}\"];",
+                        node_id(location),
+                        node_id(&neighbour),
+                        direction
+                    ));
+                }
+            }
+        }
+
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+
+    /// Serializes the rooms, with their coordinates, name, description and objects, and the
+    /// player's location to JSON. Rooms are sorted by coordinates
+    fn to_json(&self, player_location: Location) -> String {
+        let mut locations: Vec<&Location> = self.rooms.keys().collect();
+        locations.sort();
+
+        let rooms = locations
+            .iter()
+            .map(|location| {
+                let room = &self.rooms[location];
+                let mut objects: Vec<(Object, u32)> = room.objects.iter().collect();
+                objects.sort_by_key(|(object, _)| object.name());
+
+                format!(
+                    "{{\"x\":{},\"y\":{},\"z\":{},\"name\":{},\"description\":{},\"objects\":[{}]}}",
+                    location.0,
+                    location.1,
+                    location.2,
+                    room.name.as_deref().map_or("null".to_string(), json_string),
+                    room.description.as_deref().map_or("null".to_string(), json_string),
+                    objects
+                        .iter()
+                        .map(|(object, count)| format!(
+                            "{{\"object\":{},\"count\":{}}}",
+                            json_string(object.name()),
+                            count
+                        ))
+                        .collect::<Vec<String>>()
+                        .join(",")
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+
+        format!(
+            "{{\"player\":{{\"x\":{},\"y\":{},\"z\":{}}},\"rooms\":[{}]}}",
+            player_location.0, player_location.1, player_location.2, rooms
+        )
+    }
+
+    /// Checks whether the room at `location` can be filled back with rock: it must not be the prize
+    /// room, have no other player in it, be empty and be a dead end. Rooms with more than one exit
+    /// are never filled, so that no part of the dungeon gets cut off
+    fn check_fillable(&self, location: Location) -> Result<(), GameError> {
+        let room = self.rooms.get(&location).ok_or(GameError::NoExit)?;
+
+        if self.goals.contains(&location) {
+            Err(GameError::CannotFillGoal)
+        } else if self.others.iter().any(|(at, _)| *at == location) {
+            Err(GameError::RoomOccupied)
+        } else if !room.objects.is_empty() {
+            Err(GameError::RoomNotEmpty)
+        } else if self.exits_for_room(location).len() > 1 {
+            Err(GameError::WouldCutOff)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Whether the room at `location` can be sealed automatically once the player leaves it: on
+    /// top of being fillable, it must have been dug by the player and have no name
+    fn can_seal(&self, location: Location) -> bool {
+        match self.rooms.get(&location) {
+            Some(room) => room.dug && room.name.is_none() && self.check_fillable(location).is_ok(),
+            None => false,
+        }
+    }
+}
+
+/// Collection of all the available commands to interact to the dungeon world
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Command {
+    /// Walking to the adjacent room in a direction
+    Move(Direction),
+    Help,
+    Dig,
+    Look,
+    Inventory,
+    Take,
+    Drop,
+    Equip,
+    Unequip,
+    Alias,
+    Map,
+    Map3D,
+    Where,
+    Name,
+    Seal,
+    Teleport,
+    Undo,
+    Climb,
+    Status,
+    Dot,
+    Json,
+    Fill,
+    Items,
+    Search,
+    Hint,
+    Solve,
+    Back,
+    Brief,
+    Verbose,
+    Normal,
+    Stats,
+    Repair,
+    Buy,
+    Sell,
+    Count,
+    Rest,
+    Eat,
+    Combine,
+    Validate,
+    Record,
+    End,
+    Play,
+    Restart,
+    Go,
+    Whoami,
+    Rename,
+    Give,
+}
+
+impl Command {
+    /// The main word used to issue the command
+    fn name(self) -> &'static str {
+        match self {
+            Command::Move(direction) => direction.name(),
+            Command::Help => "help",
+            Command::Dig => "dig",
+            Command::Look => "look",
+            Command::Inventory => "inventory",
+            Command::Take => "take",
+            Command::Drop => "drop",
+            Command::Equip => "equip",
+            Command::Unequip => "unequip",
+            Command::Alias => "alias",
+            Command::Map => "map",
+            Command::Map3D => "map3d",
+            Command::Where => "where",
+            Command::Name => "name",
+            Command::Seal => "seal",
+            Command::Teleport => "goto",
+            Command::Undo => "undo",
+            Command::Climb => "climb",
+            Command::Status => "status",
+            Command::Dot => "dot",
+            Command::Json => "json",
+            Command::Fill => "fill",
+            Command::Items => "items",
+            Command::Search => "search",
+            Command::Hint => "hint",
+            Command::Solve => "solve",
+            Command::Back => "back",
+            Command::Brief => "brief",
+            Command::Verbose => "verbose",
+            Command::Normal => "normal",
+            Command::Stats => "stats",
+            Command::Repair => "repair",
+            Command::Buy => "buy",
+            Command::Sell => "sell",
+            Command::Count => "count",
+            Command::Rest => "rest",
+            Command::Eat => "eat",
+            Command::Combine => "combine",
+            Command::Validate => "validate",
+            Command::Record => "record",
+            Command::End => "end",
+            Command::Play => "play",
+            Command::Restart => "restart",
+            Command::Go => "go",
+            Command::Whoami => "whoami",
+            Command::Rename => "rename",
+            Command::Give => "give",
+        }
+    }
+
+    /// Whether the command can change the dungeon or the player, and so can be undone
+    fn mutates(self) -> bool {
+        match self {
+            Command::Move(_)
+            | Command::Dig
+            | Command::Take
+            | Command::Drop
+            | Command::Equip
+            | Command::Unequip
+            | Command::Name
+            | Command::Teleport
+            | Command::Climb
+            | Command::Fill
+            | Command::Search
+            | Command::Back
+            | Command::Go
+            | Command::Repair
+            | Command::Buy
+            | Command::Sell
+            | Command::Rest
+            | Command::Eat
+            | Command::Combine
+            | Command::Rename
+            | Command::Give => true,
+            Command::Help
+            | Command::Look
+            | Command::Inventory
+            | Command::Alias
+            | Command::Map
+            | Command::Map3D
+            | Command::Where
+            | Command::Seal
+            | Command::Undo
+            | Command::Status
+            | Command::Dot
+            | Command::Json
+            | Command::Items
+            | Command::Hint
+            | Command::Solve
+            | Command::Brief
+            | Command::Verbose
+            | Command::Normal
+            | Command::Stats
+            | Command::Count
+            | Command::Validate
+            | Command::Record
+            | Command::End
+            | Command::Play
+            | Command::Restart
+            | Command::Whoami => false,
+        }
+    }
+
+    /// The function running the command. Every command has exactly one, so adding a command
+    /// takes a new entry here and the compiler points out when one is missing
+    fn handler(self) -> Handler {
+        match self {
+            Command::Move(_) => |game, command, _, _| match command {
+                Command::Move(direction) => game.go(direction),
+                _ => unreachable!("{} is not a move", command.name()),
+            },
+            Command::Help => |game, _, args, _| help(&game.command_aliases, args),
+            Command::Alias => |game, _, args, _| alias(&mut game.command_aliases, args),
+            Command::Look => |game, _, _, _| look(&game.player, &game.dungeon),
+            Command::Items => |game, _, _, _| items(&game.player, &game.dungeon),
+            Command::Hint => |game, _, _, _| hint(&game.player, &game.dungeon),
+            Command::Stats => |game, _, _, _| stats(&game.player, game.playtime()),
+            Command::Repair => |game, _, _, _| repair(&mut game.player, &game.dungeon),
+            Command::Count => {
+                |game, _, args, _| count(&game.player, args, &game.generation.objects)
+            }
+            Command::Rest => |game, _, _, _| rest(&mut game.player, game.rng.as_mut()),
+            Command::Eat => |game, _, args, _| eat(&mut game.player, args),
+            Command::Combine => {
+                |game, _, args, _| combine(&mut game.player, args, &game.generation.objects)
+            }
+            Command::Validate => |game, _, _, _| validate(&game.player, &game.dungeon, game.debug),
+            Command::Record => |game, _, args, _| game.record(args),
+            Command::End => |game, _, _, _| game.end_recording(),
+            Command::Play => |game, _, args, _| game.play(args),
+            Command::Restart => |game, _, _, _| game.ask_restart(),
+            Command::Buy => |game, _, args, _| {
+                buy(
+                    &mut game.player,
+                    &game.dungeon,
+                    args,
+                    &game.generation.objects,
+                )
+            },
+            Command::Sell => |game, _, args, _| {
+                sell(
+                    &mut game.player,
+                    &game.dungeon,
+                    args,
+                    &game.generation.objects,
+                )
+            },
+            Command::Back => {
+                |game, _, _, _| back(&mut game.player, &mut game.dungeon, game.verbosity)
+            }
+            Command::Go => {
+                |game, _, args, _| walk(&mut game.player, &mut game.dungeon, game.verbosity, args)
+            }
+            Command::Brief => |game, _, _, _| game.set_verbosity(Verbosity::Brief),
+            Command::Normal => |game, _, _, _| game.set_verbosity(Verbosity::Normal),
+            Command::Verbose => |game, _, _, _| game.set_verbosity(Verbosity::Verbose),
+            Command::Solve => |game, _, _, _| solve(&game.player, &game.dungeon),
+            Command::Search => {
+                |game, _, _, _| search(&game.player, &mut game.dungeon, game.rng.as_mut())
+            }
+            Command::Take => |game, _, args, _| {
+                take(
+                    &mut game.player,
+                    &mut game.dungeon,
+                    args,
+                    &game.generation.objects,
+                )
+            },
+            Command::Drop => |game, _, args, _| {
+                drop(
+                    &mut game.player,
+                    &mut game.dungeon,
+                    args,
+                    &game.generation.objects,
+                )
+            },
+            Command::Inventory => |game, _, _, _| inventory(&game.player),
+            Command::Dig => |game, _, args, _| {
+                dig(
+                    &mut game.player,
+                    &mut game.dungeon,
+                    game.rng.as_mut(),
+                    &game.generation,
+                    args,
+                )
+            },
+            Command::Equip => {
+                |game, _, args, _| equip(&mut game.player, args, &game.generation.objects)
+            }
+            Command::Unequip => |game, _, _, _| unequip(&mut game.player),
+            Command::Map => |game, _, _, _| map(&game.player, &game.dungeon),
+            Command::Map3D => |game, _, _, _| map_3d(&game.player, &game.dungeon),
+            Command::Where => |game, _, _, _| where_am_i(&game.player, &game.dungeon),
+            Command::Name => |game, _, _, raw_args| name(&game.player, &mut game.dungeon, raw_args),
+            Command::Seal => |game, _, args, _| seal(&mut game.auto_seal, args),
+            Command::Teleport => {
+                |game, _, args, _| teleport(&mut game.player, &mut game.dungeon, game.debug, args)
+            }
+            Command::Undo => {
+                |game, _, _, _| undo(&mut game.history, &mut game.player, &mut game.dungeon)
+            }
+            Command::Climb => {
+                |game, _, args, _| climb(&mut game.player, &mut game.dungeon, game.verbosity, args)
+            }
+            Command::Status => |game, _, _, _| status(&game.player),
+            Command::Dot => |game, _, _, _| Ok(game.dungeon.to_dot()),
+            Command::Json => |game, _, _, _| Ok(game.dungeon.to_json(game.player.location)),
+            Command::Fill => |game, _, args, _| fill(&game.player, &mut game.dungeon, args),
+            Command::Whoami => |game, _, _, _| Ok(game.whoami()),
+            Command::Rename => {
+                |game, _, _, raw_args| rename(&mut game.player, &game.dungeon, raw_args)
+            }
+            Command::Give => |game, _, args, _| game.give(args),
+        }
+    }
+}
+
+/// Returns the list of all the default command aliases
+fn default_aliases() -> CommandAliases {
+    vec![
+        (
+            vec!["n".to_string(), "north".to_string()]
+                .into_iter()
+                .collect(),
+            Command::Move(Direction::North),
+        ),
+        (
+            vec!["s".to_string(), "south".to_string()]
+                .into_iter()
+                .collect(),
+            Command::Move(Direction::South),
+        ),
+        (
+            vec!["w".to_string(), "west".to_string()]
+                .into_iter()
+                .collect(),
+            Command::Move(Direction::West),
+        ),
+        (
+            vec!["e".to_string(), "east".to_string()]
+                .into_iter()
+                .collect(),
+            Command::Move(Direction::East),
+        ),
+        (
+            vec!["d".to_string(), "down".to_string()]
+                .into_iter()
+                .collect(),
+            Command::Move(Direction::Down),
+        ),
+        (
+            vec!["u".to_string(), "up".to_string()]
+                .into_iter()
+                .collect(),
+            Command::Move(Direction::Up),
+        ),
+        (
+            vec!["help".to_string()].into_iter().collect(),
+            Command::Help,
+        ),
+        (vec!["dig".to_string()].into_iter().collect(), Command::Dig),
+        (
+            vec!["l".to_string(), "look".to_string()]
+                .into_iter()
+                .collect(),
+            Command::Look,
+        ),
+        (
+            vec!["i".to_string(), "inventory".to_string()]
+                .into_iter()
+                .collect(),
+            Command::Inventory,
+        ),
+        (
+            vec!["take".to_string()].into_iter().collect(),
+            Command::Take,
+        ),
+        (
+            vec!["drop".to_string()].into_iter().collect(),
+            Command::Drop,
+        ),
+        (
+            vec!["equip".to_string()].into_iter().collect(),
+            Command::Equip,
+        ),
+        (
+            vec!["unequip".to_string()].into_iter().collect(),
+            Command::Unequip,
+        ),
+        (
+            vec!["alias".to_string()].into_iter().collect(),
+            Command::Alias,
+        ),
+        (vec!["map".to_string()].into_iter().collect(), Command::Map),
+        (
+            vec!["map3d".to_string()].into_iter().collect(),
+            Command::Map3D,
+        ),
+        (
+            vec!["where".to_string(), "coords".to_string()]
+                .into_iter()
+                .collect(),
+            Command::Where,
+        ),
+        (
+            vec!["name".to_string()].into_iter().collect(),
+            Command::Name,
+        ),
+        (
+            vec!["seal".to_string()].into_iter().collect(),
+            Command::Seal,
+        ),
+        (
+            vec!["tp".to_string(), "goto".to_string()]
+                .into_iter()
+                .collect(),
+            Command::Teleport,
+        ),
+        (
+            vec!["undo".to_string()].into_iter().collect(),
+            Command::Undo,
+        ),
+        (
+            vec!["climb".to_string()].into_iter().collect(),
+            Command::Climb,
+        ),
+        (
+            vec!["status".to_string()].into_iter().collect(),
+            Command::Status,
+        ),
+        (vec!["dot".to_string()].into_iter().collect(), Command::Dot),
+        (
+            vec!["json".to_string()].into_iter().collect(),
+            Command::Json,
+        ),
+        (
+            vec!["fill".to_string()].into_iter().collect(),
+            Command::Fill,
+        ),
+        (
+            vec!["items".to_string(), "floor".to_string()]
+                .into_iter()
+                .collect(),
+            Command::Items,
+        ),
+        (
+            vec!["search".to_string()].into_iter().collect(),
+            Command::Search,
+        ),
+        (
+            vec!["hint".to_string()].into_iter().collect(),
+            Command::Hint,
+        ),
+        (
+            vec!["solve".to_string()].into_iter().collect(),
+            Command::Solve,
+        ),
+        (
+            vec!["back".to_string()].into_iter().collect(),
+            Command::Back,
+        ),
+        (
+            vec!["brief".to_string()].into_iter().collect(),
+            Command::Brief,
+        ),
+        (
+            vec!["verbose".to_string()].into_iter().collect(),
+            Command::Verbose,
+        ),
+        (
+            vec!["normal".to_string()].into_iter().collect(),
+            Command::Normal,
+        ),
+        (
+            vec!["stats".to_string()].into_iter().collect(),
+            Command::Stats,
+        ),
+        (
+            vec!["repair".to_string()].into_iter().collect(),
+            Command::Repair,
+        ),
+        (vec!["buy".to_string()].into_iter().collect(), Command::Buy),
+        (
+            vec!["sell".to_string()].into_iter().collect(),
+            Command::Sell,
+        ),
+        (
+            vec!["count".to_string()].into_iter().collect(),
+            Command::Count,
+        ),
+        (
+            vec!["rest".to_string(), "sleep".to_string()]
+                .into_iter()
+                .collect(),
+            Command::Rest,
+        ),
+        (vec!["eat".to_string()].into_iter().collect(), Command::Eat),
+        (
+            vec!["combine".to_string(), "craft".to_string()]
+                .into_iter()
+                .collect(),
+            Command::Combine,
+        ),
+        (
+            vec!["validate".to_string()].into_iter().collect(),
+            Command::Validate,
+        ),
+        (
+            vec!["record".to_string()].into_iter().collect(),
+            Command::Record,
+        ),
+        (vec!["end".to_string()].into_iter().collect(), Command::End),
+        (
+            vec!["play".to_string()].into_iter().collect(),
+            Command::Play,
+        ),
+        (
+            vec!["restart".to_string()].into_iter().collect(),
+            Command::Restart,
+        ),
+        (
+            vec!["go".to_string(), "again".to_string()]
+                .into_iter()
+                .collect(),
+            Command::Go,
+        ),
+        (
+            vec!["whoami".to_string()].into_iter().collect(),
+            Command::Whoami,
+        ),
+        (
+            vec!["rename".to_string()].into_iter().collect(),
+            Command::Rename,
+        ),
+        (
+            vec!["give".to_string()].into_iter().collect(),
+            Command::Give,
+        ),
+    ]
+}
+
+/// Splits a line of input into words. Text between double quotes is kept as a single word, so
+/// `name "Hall of Echoes"` yields `name` and `Hall of Echoes`; a quote left open runs until the end
+/// of the line
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut quoted = false;
+
+    for c in input.chars() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+            quoted = true;
+        } else if c.is_whitespace() && !in_quotes {
+            if !current.is_empty() || quoted {
+                tokens.push(std::mem::take(&mut current));
+            }
+            quoted = false;
+        } else {
+            current.push(c);
+        }
+    }
+
+    if !current.is_empty() || quoted {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+/// Tries to parse a string to a command also taking into account the aliases
+fn find_command(command: &str, aliases: &[(HashSet<String>, Command)]) -> Option<Command> {
+    let command = command.to_lowercase();
+
+    aliases.iter().find(|a| a.0.contains(&command)).map(|a| a.1)
+}
+
+/// Number of single character insertions, deletions or substitutions needed to turn `a` into `b`
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut distances = (0..=b.len()).collect::<Vec<usize>>();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut previous_diagonal = distances[0];
+        distances[0] = i + 1;
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous_diagonal + if a_char == *b_char { 0 } else { 1 };
+            previous_diagonal = distances[j + 1];
+            distances[j + 1] = substitution.min(distances[j] + 1).min(distances[j + 1] + 1);
+        }
+    }
+
+    distances[b.len()]
+}
+
+/// The alias closest to a mistyped command, if any is within `SUGGESTION_DISTANCE` edits. The
+/// edits must also be fewer than the characters of the alias: any single letter is one edit away
+/// from `n`, which would otherwise be suggested for all sorts of gibberish. Ties go to the longest
+/// alias, then to the alphabetically first one
+fn closest_alias(command: &str, aliases: &[(HashSet<String>, Command)]) -> Option<String> {
+    let command = command.to_lowercase();
+
+    aliases
+        .iter()
+        .flat_map(|a| a.0.iter())
+        .map(|alias| {
+            (
+                levenshtein(&command, alias),
+                Reverse(alias.chars().count()),
+                alias,
+            )
+        })
+        .filter(|(distance, length, _)| *distance <= SUGGESTION_DISTANCE && *distance < length.0)
+        .min()
+        .map(|(_, _, alias)| alias.clone())
+}
+
+/// Everything that can go wrong while running a command
+#[derive(Debug)]
+enum GameError {
+    /// The command was issued with missing or invalid arguments; holds the id of the message
+    /// telling how to use it
+    Usage(&'static str),
+    /// The input does not match any command or alias; holds the closest alias, if any
+    UnknownCommand(Option<String>),
+    /// Tried to alias a command that does not exist
+    UnknownAliasTarget(String),
+    /// Tried to take something from an empty room
+    NothingToTake,
+    /// Tried to drop something with an empty inventory
+    NotCarryingAnything,
+    /// The object to take is not in the room
+    NotInRoom,
+    /// The object to drop is not in the inventory
+    NotInInventory,
+    /// The object to equip is not in the inventory
+    CannotEquip,
+    /// Tried to unequip with nothing equipped
+    NothingEquipped,
+    /// Tried to dig with nothing equipped
+    BareHands,
+    /// Tried to dig with an object that is not a sledge
+    CannotDigWith(Object),
+    /// The argument is not a valid direction
+    UnknownDirection,
+    /// Tried to dig towards an existing room
+    ExitAlreadyExists(Direction),
+    /// Tried to dig or move past the largest coordinates there can be
+    EdgeOfWorld,
+    /// The player is somewhere there is no room, which should never happen
+    NoRoom,
+    /// Tried to restart while sharing the dungeon with other players
+    SharedRestart,
+    /// Tried to go up without a ladder
+    NeedLadder,
+    /// Tried to move towards solid rock
+    NoExit,
+    /// Tried to go down into a pit without a rope
+    NeedRope,
+    /// Tried to dig past the maximum size of the dungeon
+    RockTooHard,
+    /// Tried to use the name of a command as an alias for another one
+    ReservedWord(Command),
+    /// Tried to use an alias already standing for another command; holds the alias and the command
+    AliasTaken(String, Command),
+    /// Tried to buy or sell outside of a merchant's room
+    NoTrader,
+    /// Tried to buy or sell something the merchant doesn't deal in
+    NotForSale,
+    /// Tried to repair a sledge outside of a forge
+    NoForge,
+    /// Tried to repair a sledge that isn't worn
+    NothingToRepair,
+    /// Tried to pay more gold than carried; holds the price
+    NotEnoughGold(u32),
+    /// Looked for a prize after taking all of them
+    NoPrizeLeft,
+    /// Tried to go back without having moved yet
+    NowhereToGoBack,
+    /// Tried to keep walking the same way without having walked anywhere yet
+    NotFacing,
+    /// Tried to go through a locked passage without a key
+    Locked,
+    /// Tried to rest at full health
+    WellRested,
+    /// Tried to combine objects that make nothing together
+    NoRecipe,
+    /// Tried to combine objects without carrying all of them
+    MissingIngredients,
+    /// Tried to eat without carrying food
+    NoFood,
+    /// Tried to eat without being hungry at all
+    NotHungry,
+    /// Tried to fill the prize room
+    CannotFillGoal,
+    /// Tried to fill a room with objects in it
+    RoomNotEmpty,
+    /// Tried to fill a room leading to other rooms
+    WouldCutOff,
+    /// Tried to dig or fill a room another player stands in
+    RoomOccupied,
+    /// Tried to take the name of another player; holds the name
+    NameTaken(String),
+    /// Tried to give something to a player who isn't in the room; holds their name
+    NobodyToGiveTo(String),
+    /// Tried to teleport without the `--debug` flag
+    TeleportDisabled,
+    /// Tried to validate the game without `--debug`
+    ValidateDisabled,
+    /// Tried to start recording while already recording
+    AlreadyRecording,
+    /// Tried to stop recording without recording
+    NotRecording,
+    /// Tried to play a macro never recorded; holds its name
+    UnknownMacro(String),
+    /// Tried to play a macro from within itself; holds its name
+    MacroLoop(String),
+    /// Tried to undo with no command left to revert
+    NothingToUndo,
+    /// Asked help about a command that does not exist; holds the list of the available ones
+    UnknownHelpTopic(String),
+}
+
+impl Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            GameError::Usage(usage) => write!(f, "{}", message(usage)),
+            GameError::UnknownCommand(None) => write!(f, "{}", message("error.unknown-command")),
+            GameError::UnknownCommand(Some(alias)) => write!(
+                f,
+                "{}",
+                message_with("error.did-you-mean", &[("alias", alias)])
+            ),
+            GameError::UnknownAliasTarget(command) => write!(
+                f,
+                "{}",
+                message_with("error.unknown-alias-target", &[("command", command)])
+            ),
+            GameError::NothingToTake => write!(f, "{}", message("error.nothing-to-take")),
+            GameError::NotCarryingAnything => {
+                write!(f, "{}", message("error.not-carrying-anything"))
+            }
+            GameError::NotInRoom => write!(f, "{}", message("error.not-in-room")),
+            GameError::NotInInventory => write!(f, "{}", message("error.not-in-inventory")),
+            GameError::CannotEquip => write!(f, "{}", message("error.cannot-equip")),
+            GameError::NothingEquipped => write!(f, "{}", message("error.nothing-equipped")),
+            GameError::BareHands => write!(f, "{}", message("error.bare-hands")),
+            GameError::CannotDigWith(object) => write!(
+                f,
+                "{}",
+                message_with("error.cannot-dig-with", &[("object", object)])
+            ),
+            GameError::UnknownDirection => write!(f, "{}", message("error.unknown-direction")),
+            GameError::ExitAlreadyExists(direction) => write!(
+                f,
+                "{}",
+                message_with("error.exit-already-exists", &[("direction", direction)])
+            ),
+            GameError::EdgeOfWorld => write!(f, "{}", message("error.edge-of-world")),
+            GameError::SharedRestart => write!(f, "{}", message("error.shared-restart")),
+            GameError::NoRoom => write!(f, "{}", message("error.no-room")),
+            GameError::NeedLadder => write!(f, "{}", message("error.need-ladder")),
+            GameError::NoExit => write!(f, "{}", message("error.no-exit")),
+            GameError::NeedRope => write!(f, "{}", message("error.need-rope")),
+            GameError::RockTooHard => write!(f, "{}", message("error.rock-too-hard")),
+            GameError::ReservedWord(command) => write!(
+                f,
+                "{}",
+                message_with("error.reserved-word", &[("command", &command.name())])
+            ),
+            GameError::AliasTaken(alias, command) => write!(
+                f,
+                "{}",
+                message_with(
+                    "error.alias-taken",
+                    &[("alias", alias), ("command", &command.name())]
+                )
+            ),
+            GameError::NoTrader => write!(f, "{}", message("error.no-trader")),
+            GameError::NotForSale => write!(f, "{}", message("error.not-for-sale")),
+            GameError::NoForge => write!(f, "{}", message("error.no-forge")),
+            GameError::NothingToRepair => write!(f, "{}", message("error.nothing-to-repair")),
+            GameError::NotEnoughGold(price) => write!(
+                f,
+                "{}",
+                message_with(
+                    "error.not-enough-gold",
+                    &[("price", &describe_price(*price))]
+                )
+            ),
+            GameError::NoPrizeLeft => write!(f, "{}", message("error.no-prize-left")),
+            GameError::NowhereToGoBack => write!(f, "{}", message("error.nowhere-to-go-back")),
+            GameError::NotFacing => write!(f, "{}", message("error.not-facing")),
+            GameError::Locked => write!(f, "{}", message("error.locked")),
+            GameError::WellRested => write!(f, "{}", message("error.well-rested")),
+            GameError::NoRecipe => write!(f, "{}", message("error.no-recipe")),
+            GameError::MissingIngredients => write!(f, "{}", message("error.missing-ingredients")),
+            GameError::NoFood => write!(f, "{}", message("error.no-food")),
+            GameError::NotHungry => write!(f, "{}", message("error.not-hungry")),
+            GameError::CannotFillGoal => write!(f, "{}", message("error.cannot-fill-goal")),
+            GameError::RoomNotEmpty => write!(f, "{}", message("error.room-not-empty")),
+            GameError::WouldCutOff => write!(f, "{}", message("error.would-cut-off")),
+            GameError::RoomOccupied => write!(f, "{}", message("error.room-occupied")),
+            GameError::NameTaken(name) => {
+                write!(f, "{}", message_with("error.name-taken", &[("name", name)]))
+            }
+            GameError::NobodyToGiveTo(name) => write!(
+                f,
+                "{}",
+                message_with("error.nobody-to-give-to", &[("name", name)])
+            ),
+            GameError::TeleportDisabled => write!(f, "{}", message("error.teleport-disabled")),
+            GameError::ValidateDisabled => write!(f, "{}", message("error.validate-disabled")),
+            GameError::AlreadyRecording => write!(f, "{}", message("error.already-recording")),
+            GameError::NotRecording => write!(f, "{}", message("error.not-recording")),
+            GameError::UnknownMacro(name) => write!(
+                f,
+                "{}",
+                message_with("error.unknown-macro", &[("name", name)])
+            ),
+            GameError::MacroLoop(name) => {
+                write!(f, "{}", message_with("error.macro-loop", &[("name", name)]))
+            }
+            GameError::NothingToUndo => write!(f, "{}", message("error.nothing-to-undo")),
+            GameError::UnknownHelpTopic(commands) => write!(
+                f,
+                "{}",
+                message_with("error.unknown-help-topic", &[("commands", commands)])
+            ),
+        }
+    }
+}
+
+/// What running a command produces: the text to show to the player or what went wrong
+type CommandResult = Result<String, GameError>;
+
+/// Runs a command on the game, given the command itself and its arguments both lowercased and as
+/// they were typed
+type Handler = fn(&mut Game, Command, &[&str], &[&str]) -> CommandResult;
+
+/// Usage and rules of a single command
+fn command_help(command: Command) -> String {
+    message(match command {
+        Command::Move(Direction::Down) => "help.down",
+        Command::Move(Direction::Up) => "help.up",
+        Command::Move(_) => "help.move",
+        Command::Help => "help.help",
+        Command::Dig => "help.dig",
+        Command::Look => "help.look",
+        Command::Items => "help.items",
+        Command::Search => "help.search",
+        Command::Hint => "help.hint",
+        Command::Back => "help.back",
+        Command::Go => "help.go",
+        Command::Brief => "help.brief",
+        Command::Repair => "help.repair",
+        Command::Buy => "help.buy",
+        Command::Sell => "help.sell",
+        Command::Count => "help.count",
+        Command::Combine => "help.combine",
+        Command::Eat => "help.eat",
+        Command::Rest => "help.rest",
+        Command::Stats => "help.stats",
+        Command::Verbose => "help.verbose",
+        Command::Normal => "help.normal",
+        Command::Solve => "help.solve",
+        Command::Inventory => "help.inventory",
+        Command::Take => "help.take",
+        Command::Drop => "help.drop",
+        Command::Equip => "help.equip",
+        Command::Unequip => "help.unequip",
+        Command::Alias => "help.alias",
+        Command::Map => "help.map",
+        Command::Map3D => "help.map3d",
+        Command::Where => "help.where",
+        Command::Name => "help.name",
+        Command::Seal => "help.seal",
+        Command::Teleport => "help.teleport",
+        Command::Record => "help.record",
+        Command::End => "help.end",
+        Command::Play => "help.play",
+        Command::Restart => "help.restart",
+        Command::Validate => "help.validate",
+        Command::Undo => "help.undo",
+        Command::Climb => "help.climb",
+        Command::Status => "help.status",
+        Command::Dot => "help.dot",
+        Command::Json => "help.json",
+        Command::Fill => "help.fill",
+        Command::Whoami => "help.whoami",
+        Command::Rename => "help.rename",
+        Command::Give => "help.give",
+    })
+}
+
+/// Returns the help string, or the help of a single command if one is given
+fn help(command_aliases: &CommandAliases, args: &[&str]) -> CommandResult {
+    match args.first() {
+        None => Ok(message_with(
+            "help",
+            &[(
+                "commands",
+                &default_aliases()
+                    .iter()
+                    .map(|(_, command)| *command)
+                    // Moves are summed up as directions, and debugging commands are left out
+                    .filter(|command| {
+                        !matches!(
+                            command,
+                            Command::Move(_) | Command::Teleport | Command::Validate
+                        )
+                    })
+                    .map(Command::name)
+                    .collect::<Vec<&str>>()
+                    .join(", "),
+            )],
+        )),
+        Some(command) => find_command(command, command_aliases)
+            .map(command_help)
+            .ok_or_else(|| {
+                GameError::UnknownHelpTopic(
+                    default_aliases()
+                        .iter()
+                        .map(|a| a.1.name())
+                        .collect::<Vec<&str>>()
+                        .join(", "),
+                )
+            }),
+    }
+}
+
+/// Defines new aliases for a command, like `alias dig d dg tunnel`. With several aliases each one
+/// is reported on its own line, and the ones that can't be added don't stop the others
+fn alias(command_aliases: &mut CommandAliases, args: &[&str]) -> CommandResult {
+    if args.len() < 2 {
+        return Err(GameError::Usage("usage.alias"));
+    }
+
+    let command = args[0].to_lowercase();
+    let target = find_command(&command, command_aliases)
+        .ok_or_else(|| GameError::UnknownAliasTarget(command.clone()))?;
+
+    if args.len() == 2 {
+        return add_alias(command_aliases, target, &command, &args[1].to_lowercase());
+    }
+
+    Ok(args[1..]
+        .iter()
+        .map(|new_alias| {
+            let new_alias = new_alias.to_lowercase();
+
+            add_alias(command_aliases, target, &command, &new_alias)
+                .unwrap_or_else(|error| error.to_string())
+        })
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+/// Adds a single alias for `target`, which was typed as `command`. An alias can only stand for one
+/// command: taking the name or any of the aliases of another command is refused
+fn add_alias(
+    command_aliases: &mut CommandAliases,
+    target: Command,
+    command: &str,
+    new_alias: &str,
+) -> CommandResult {
+    for (aliases, other) in command_aliases.iter() {
+        if *other == target {
+            continue;
+        }
+
+        if other.name() == new_alias {
+            return Err(GameError::ReservedWord(*other));
+        }
+        if aliases.contains(new_alias) {
+            return Err(GameError::AliasTaken(new_alias.to_string(), *other));
+        }
+    }
+
+    let aliases = &mut command_aliases
+        .iter_mut()
+        .find(|ca| ca.1 == target)
+        .expect("Every command has aliases")
+        .0;
+
+    if aliases.contains(new_alias) {
+        Ok(message_with(
+            "alias.unchanged",
+            &[("alias", &new_alias), ("command", &target.name())],
+        ))
+    } else {
+        aliases.insert(new_alias.to_string());
+
+        Ok(message_with(
+            "alias.added",
+            &[("alias", &new_alias), ("command", &command)],
+        ))
+    }
+}
+
+/// Gives a name to the current room. The name is kept exactly as typed, so `args` should not be
+/// lowercased
+fn name(player: &Player, dungeon: &mut Dungeon, args: &[&str]) -> CommandResult {
+    if args.is_empty() {
+        return Err(GameError::Usage("usage.name"));
+    }
+
+    let name = args.join(" ");
+
+    dungeon
+        .rooms
+        .get_mut(&player.location)
+        .ok_or(GameError::NoRoom)?
+        .name = Some(name.clone());
+
+    Ok(message_with("name.set", &[("name", &name)]))
+}
+
+/// Gives a name to the player, for the others sharing the dungeon to see. The name is kept
+/// exactly as typed, so `args` should not be lowercased, and must not be someone else's already
+fn rename(player: &mut Player, dungeon: &Dungeon, args: &[&str]) -> CommandResult {
+    if args.is_empty() {
+        return Err(GameError::Usage("usage.rename"));
+    }
+
+    let name = args.join(" ");
+    if dungeon
+        .others
+        .iter()
+        .any(|(_, other)| other.to_lowercase() == name.to_lowercase())
+    {
+        return Err(GameError::NameTaken(name));
+    }
+
+    player.name = Some(name.clone());
+    Ok(message_with("rename.set", &[("name", &name)]))
+}
+
+/// Lists the objects lying on the floor of a room, if there are any
+fn describe_floor(room: &Room) -> Option<String> {
+    if room.objects.is_empty() {
+        None
+    } else {
+        Some(message_with(
+            "room.floor",
+            &[("objects", &room.objects.list())],
+        ))
+    }
+}
+
+/// Lists only the objects in the current room
+fn items(player: &Player, dungeon: &Dungeon) -> CommandResult {
+    let room = dungeon
+        .rooms
+        .get(&player.location)
+        .ok_or(GameError::NoRoom)?;
+
+    Ok(describe_floor(room).unwrap_or_else(|| message("room.empty-floor")))
+}
+
+/// Describes the current rooom
+fn look(player: &Player, dungeon: &Dungeon) -> CommandResult {
+    Ok(format!(
+        "{}\n{}",
+        describe_room(player, dungeon, Verbosity::Verbose)?,
+        compass(dungeon, player.location)
+    ))
+}
+
+/// A line marking which directions lead out of the room at `location`, one box per direction in
+/// the order of `DIRECTION_MAPPING`, like `Compass: [N][ ][ ][E][D][ ]`
+fn compass(dungeon: &Dungeon, location: Location) -> String {
+    let exits = dungeon.exits_for_room(location);
+    let boxes = DIRECTION_MAPPING
+        .iter()
+        .map(|(_, direction)| {
+            if exits.contains(direction) {
+                format!("[{}]", direction.name()[..1].to_uppercase())
+            } else {
+                "[ ]".to_string()
+            }
+        })
+        .collect::<String>();
+
+    message_with("room.compass", &[("boxes", &boxes)])
+}
+
+/// Describes the current room when walking in: either fully like `look` or, in brief mode, just
+/// its name (or coordinates) and its exits. `Verbosity::Normal` must be resolved to one of the two
+/// by the caller, and is shown in full
+fn describe_room(player: &Player, dungeon: &Dungeon, verbosity: Verbosity) -> CommandResult {
+    let room = dungeon
+        .rooms
+        .get(&player.location)
+        .ok_or(GameError::NoRoom)?;
+    let mut description = String::new();
+
+    match verbosity {
+        Verbosity::Verbose | Verbosity::Normal => {
+            if let Some(name) = &room.name {
+                description.push_str(&format!("{}. ", color::room(name)));
+            }
+
+            if let Some(room_description) = &room.description {
+                description.push_str(&color::room(room_description));
+            } else {
+                description.push_str(&color::room(&message_with(
+                    "room.at",
+                    &[("location", &format!("{:?}", player.location))],
+                )));
+            }
+
+            if room.pit {
+                description.push_str(&format!(" {}", message("room.pit")));
+            }
+
+            if room.forge {
+                description.push_str(&format!(" {}", message("room.forge")));
+            }
+
+            if room.vendor {
+                description.push_str(&format!(" {}", message("room.vendor")));
+            }
+
+            if room.objects.can_climb() {
+                description.push_str(&format!(" {}", message("room.ladder")));
+            }
+
+            for (_, name) in dungeon
+                .others
+                .iter()
+                .filter(|(at, _)| *at == player.location)
+            {
+                description.push_str(&format!(
+                    " {}",
+                    message_with("room.other", &[("name", name)])
+                ));
+            }
+
+            if let Some(floor) = describe_floor(room) {
+                description.push(' ');
+                description.push_str(&floor);
+            }
+        }
+        Verbosity::Brief => match &room.name {
+            Some(name) => description.push_str(&format!("{}.", color::room(name))),
+            None => description.push_str(&color::room(&message_with(
+                "room.at",
+                &[("location", &format!("{:?}", player.location))],
+            ))),
+        },
+    }
+
+    let (locked_exits, room_exits): (Vec<Direction>, Vec<Direction>) = dungeon
+        .exits_for_room(player.location)
+        .into_iter()
+        .partition(|direction| room.locked.contains(direction));
+    description.push_str(&match room_exits.len() {
+        0 if !locked_exits.is_empty() => String::new(),
+        0 => format!(" {}", message("room.no-exits")),
+        1 => format!(
+            " {}",
+            message_with(
+                "room.one-exit",
+                &[("exit", &color::direction(&room_exits[0].to_string()))],
+            )
+        ),
+        _ => format!(
+            " {}",
+            message_with(
+                "room.exits",
+                &[(
+                    "exits",
+                    &room_exits
+                        .iter()
+                        .map(|o| color::direction(&o.to_string()))
+                        .collect::<Vec<String>>()
+                        .join(", "),
+                )],
+            )
+        ),
+    });
+    for direction in locked_exits {
+        description.push_str(&format!(
+            " {}",
+            message_with(
+                "room.locked-exit",
+                &[("direction", &color::direction(&direction.to_string()))],
+            )
+        ));
+    }
+
+    Ok(description)
+}
+
+/// Id of the message telling how to use the `take` command
+const TAKE_USAGE: &str = "usage.take";
+
+/// Splits the arguments of `take`/`drop` into the optional number of objects to move and the name
+/// of the object. The first word is only a count when it is a number, which must be a positive
+/// integer; otherwise it must name an object or be `all`, and the words after it are ignored
+fn parse_quantity<'a>(
+    args: &[&'a str],
+    objects: &CustomObjects,
+    usage: &'static str,
+) -> Result<(Option<u32>, &'a str), GameError> {
+    match args {
+        [] => Err(GameError::Usage(usage)),
+        [object_name] => Ok((None, object_name)),
+        [count, object_name, ..] if count.parse::<i64>().is_ok() => match count.parse::<u32>() {
+            Ok(count) if count > 0 => Ok((Some(count), object_name)),
+            _ => Err(GameError::Usage(usage)),
+        },
+        [object_name, ..]
+            if *object_name == "all" || Object::from_string(object_name, objects).is_some() =>
+        {
+            Ok((None, object_name))
+        }
+        _ => Err(GameError::Usage(usage)),
+    }
+}
+
+/// Grabs an object lying on the floor of a room and puts it into the player's inventory. With a
+/// count, only takes up to that many objects
+fn take(
+    player: &mut Player,
+    dungeon: &mut Dungeon,
+    args: &[&str],
+    objects: &CustomObjects,
+) -> CommandResult {
+    let (count, object_name) = parse_quantity(args, objects, TAKE_USAGE)?;
+    let in_prize_room = dungeon.goals.contains(&player.location);
+
+    let room_objects = dungeon
+        .rooms
+        .get_mut(&player.location)
+        .ok_or(GameError::NoRoom)?
+        .objects
+        .borrow_mut();
+
+    if room_objects.is_empty() {
+        Err(GameError::NothingToTake)
+    } else if object_name == "all" && count.is_none() {
+        let taken = room_objects.list();
+        player.pick_up_gold(room_objects.count(Object::Gold), in_prize_room);
+        player.inventory.append(room_objects);
+
+        Ok(message_with("take.all", &[("objects", &taken)]))
+    } else {
+        match Object::from_string(object_name, objects) {
+            Some(object) if room_objects.contains(object) => {
+                let taken = room_objects.remove(object, count.unwrap_or(u32::MAX));
+                player.inventory.add(object, taken);
+                if object == Object::Gold {
+                    player.pick_up_gold(taken, in_prize_room);
+                }
+
+                match count {
+                    Some(_) => Ok(message_with(
+                        "take.some",
+                        &[("objects", &object.display_count(taken))],
+                    )),
+                    None => Ok(message("take.one")),
+                }
+            }
+            _ => Err(GameError::NotInRoom),
+        }
+    }
+}
+
+/// Id of the message telling how to use the `drop` command
+const DROP_USAGE: &str = "usage.drop";
+
+/// Id of the message telling how to use the `give` command
+const GIVE_USAGE: &str = "usage.give";
+
+/// Removes an object from the player's inventory and leaves it lying on the current room's floor.
+/// With a count, only drops up to that many objects
+fn drop(
+    player: &mut Player,
+    dungeon: &mut Dungeon,
+    args: &[&str],
+    objects: &CustomObjects,
+) -> CommandResult {
+    let (count, object_name) = parse_quantity(args, objects, DROP_USAGE)?;
+
+    let room_objects = dungeon
+        .rooms
+        .get_mut(&player.location)
+        .ok_or(GameError::NoRoom)?
+        .objects
+        .borrow_mut();
+
+    let result = if player.inventory.is_empty() {
+        Err(GameError::NotCarryingAnything)
+    } else if object_name == "all" && count.is_none() {
+        let dropped = player.inventory.list();
+        room_objects.append(&mut player.inventory);
+
+        Ok(message_with("drop.all", &[("objects", &dropped)]))
+    } else {
+        match Object::from_string(object_name, objects) {
+            Some(object) if player.inventory.contains(object) => {
+                let dropped = player.inventory.remove(object, count.unwrap_or(u32::MAX));
+                room_objects.add(object, dropped);
+
+                match count {
+                    Some(count) if count > dropped => Ok(message_with(
+                        "drop.fewer",
+                        &[("objects", &object.display_count(dropped))],
+                    )),
+                    Some(_) => Ok(message_with(
+                        "drop.some",
+                        &[("objects", &object.display_count(dropped))],
+                    )),
+                    None => Ok(message("drop.one")),
+                }
+            }
+            _ => Err(GameError::NotInInventory),
+        }
+    };
+
+    if player.unequip_if_missing() {
+        result.map(|output| format!("{}{}", output, message("drop.unequipped")))
+    } else {
+        result
+    }
+}
+
+/// Looks around the current room for hidden objects, uncovering one of them on success. Failing
+/// reads the same as there being nothing to find
+fn search<R: Rng + ?Sized>(player: &Player, dungeon: &mut Dungeon, rng: &mut R) -> CommandResult {
+    let room = dungeon
+        .rooms
+        .get_mut(&player.location)
+        .ok_or(GameError::NoRoom)?;
+
+    let hidden_object = room.hidden.iter().next().map(|(object, _)| object);
+
+    match hidden_object {
+        Some(object) if rng.gen::<f32>() < SEARCH_PROBABILITY => {
+            room.hidden.remove(object, 1);
+            room.objects.add(object, 1);
+
+            Ok(message_with("search.found", &[("object", &object)]))
+        }
+        _ => Ok(message("search.nothing")),
+    }
+}
+
+/// Has the smith of the current room repair the player's sledge or, if they have none, forge a new
+/// one, both for some gold
+fn repair(player: &mut Player, dungeon: &Dungeon) -> CommandResult {
+    let room = dungeon
+        .rooms
+        .get(&player.location)
+        .ok_or(GameError::NoRoom)?;
+    if !room.forge {
+        return Err(GameError::NoForge);
+    }
+
+    let has_sledge = player.inventory.contains(Object::Sledge);
+    let cost = if has_sledge { REPAIR_COST } else { FORGE_COST };
+
+    if has_sledge {
+        match player.sledge_durability {
+            Some(durability) if player.sledge_uses_left < durability => {}
+            _ => return Err(GameError::NothingToRepair),
+        }
+    }
+
+    if player.inventory.count(Object::Gold) < cost {
+        return Err(GameError::NotEnoughGold(cost));
+    }
+
+    player.inventory.remove(Object::Gold, cost);
+    player.unequip_if_missing();
+    if let Some(durability) = player.sledge_durability {
+        player.sledge_uses_left = durability;
+    }
+
+    if has_sledge {
+        Ok(message_with(
+            "repair.repaired",
+            &[("price", &describe_price(cost))],
+        ))
+    } else {
+        player.inventory.add(Object::Sledge, 1);
+
+        Ok(message_with(
+            "repair.forged",
+            &[("price", &describe_price(cost))],
+        ))
+    }
+}
+
+/// Describes an amount of gold to pay, like `1 gold` (rather than `some gold`)
+fn describe_price(amount: u32) -> String {
+    color::object(&message_with("price", &[("amount", &amount)]))
+}
+
+/// Buys an object from the merchant in the current room
+fn buy(
+    player: &mut Player,
+    dungeon: &Dungeon,
+    args: &[&str],
+    objects: &CustomObjects,
+) -> CommandResult {
+    if args.is_empty() {
+        return Err(GameError::Usage("usage.buy"));
+    }
+    let room = dungeon
+        .rooms
+        .get(&player.location)
+        .ok_or(GameError::NoRoom)?;
+    if !room.vendor {
+        return Err(GameError::NoTrader);
+    }
+
+    let (object, price, _) = Object::from_string(args[0], objects)
+        .and_then(|object| PRICES.iter().find(|(o, _, _)| *o == object))
+        .ok_or(GameError::NotForSale)?;
+
+    if player.inventory.count(Object::Gold) < *price {
+        return Err(GameError::NotEnoughGold(*price));
+    }
+
+    player.inventory.remove(Object::Gold, *price);
+    player.unequip_if_missing();
+    player.inventory.add(*object, 1);
+
+    Ok(message_with(
+        "trade.buy",
+        &[("object", object), ("price", &describe_price(*price))],
+    ))
+}
+
+/// Sells an object to the merchant in the current room, for less than it costs to buy it
+fn sell(
+    player: &mut Player,
+    dungeon: &Dungeon,
+    args: &[&str],
+    objects: &CustomObjects,
+) -> CommandResult {
+    if args.is_empty() {
+        return Err(GameError::Usage("usage.sell"));
+    }
+    let room = dungeon
+        .rooms
+        .get(&player.location)
+        .ok_or(GameError::NoRoom)?;
+    if !room.vendor {
+        return Err(GameError::NoTrader);
+    }
+
+    let object = Object::from_string(args[0], objects)
+        .filter(|object| player.inventory.contains(*object))
+        .ok_or(GameError::NotInInventory)?;
+    let (_, _, price) = PRICES
+        .iter()
+        .find(|(o, _, _)| *o == object)
+        .ok_or(GameError::NotForSale)?;
+
+    player.inventory.remove(object, 1);
+    player.unequip_if_missing();
+    player.inventory.add(Object::Gold, *price);
+
+    Ok(message_with(
+        "trade.sell",
+        &[("object", &object), ("price", &describe_price(*price))],
+    ))
+}
+
+/// Tells how many objects of a kind the player carries
+fn count(player: &Player, args: &[&str], objects: &CustomObjects) -> CommandResult {
+    let object = args
+        .first()
+        .and_then(|name| Object::from_string(name, objects))
+        .ok_or(GameError::Usage("usage.count"))?;
+
+    match player.inventory.count(object) {
+        0 => Ok(message_with(
+            "count.none",
+            &[("object", &color::object(object.name()))],
+        )),
+        count => Ok(message_with(
+            "count.some",
+            &[("objects", &object.display_count(count))],
+        )),
+    }
+}
+
+/// Recovers some health, up to the maximum, at the cost of a move. Sometimes a wandering creature
+/// comes by and hurts the player instead
+fn rest<R: Rng + ?Sized>(player: &mut Player, rng: &mut R) -> CommandResult {
+    if player.health >= player.max_health {
+        return Err(GameError::WellRested);
+    }
+
+    player.moves += 1;
+
+    if rng.gen::<f32>() < REST_HAZARD_PROBABILITY {
+        player.damage(HAZARD_DAMAGE);
+        return Ok(message_with(
+            "rest.bitten",
+            &[
+                ("damage", &HAZARD_DAMAGE),
+                ("health", &player.health),
+                ("max_health", &player.max_health),
+            ],
+        ));
+    }
+
+    player.health = (player.health + REST_HEALTH).min(player.max_health);
+    Ok(message_with(
+        "rest.rested",
+        &[
+            ("health", &player.health),
+            ("max_health", &player.max_health),
+        ],
+    ))
+}
+
+/// Makes a new object out of the ones named, if they are the ingredients of a recipe and the player
+/// carries all of them. The ingredients are used up
+fn combine(player: &mut Player, args: &[&str], objects: &CustomObjects) -> CommandResult {
+    let mut ingredients = args
+        .iter()
+        .map(|name| Object::from_string(name, objects).ok_or(GameError::NoRecipe))
+        .collect::<Result<Vec<Object>, GameError>>()?;
+    if ingredients.len() < 2 {
+        return Err(GameError::Usage("usage.combine"));
+    }
+    ingredients.sort();
+
+    let result = RECIPES
+        .iter()
+        .find(|(recipe, _)| *recipe == ingredients.as_slice())
+        .map(|(_, result)| *result)
+        .ok_or(GameError::NoRecipe)?;
+
+    let needed = Inventory::from_iter(ingredients);
+    if needed
+        .iter()
+        .any(|(object, count)| player.inventory.count(object) < count)
+    {
+        return Err(GameError::MissingIngredients);
+    }
+
+    for (object, count) in needed.iter() {
+        player.inventory.remove(object, count);
+    }
+    player.unequip_if_missing();
+    player.inventory.add(result, 1);
+
+    Ok(message_with(
+        "combine.done",
+        &[("ingredients", &needed.list()), ("result", &result)],
+    ))
+}
+
+/// Eats one of the rations carried, taking some hunger away
+fn eat(player: &mut Player, args: &[&str]) -> CommandResult {
+    match args.first() {
+        None | Some(&"food") | Some(&"rations") => {}
+        Some(_) => return Err(GameError::Usage("usage.eat")),
+    }
+
+    if !player.inventory.contains(Object::Food) {
+        return Err(GameError::NoFood);
+    }
+    if player.hunger == 0 {
+        return Err(GameError::NotHungry);
+    }
+
+    player.inventory.remove(Object::Food, 1);
+    player.unequip_if_missing();
+    player.hunger = player.hunger.saturating_sub(FOOD_NOURISHMENT);
+
+    if player.hunger == 0 {
+        Ok(message("eat.full"))
+    } else {
+        Ok(message("eat.still-hungry"))
+    }
+}
+
+/// Lists the objects currently carried by the player, pointing out the equipped one
+fn inventory(player: &Player) -> CommandResult {
+    if player.inventory.is_empty() {
+        return Ok(message("inventory.empty"));
+    }
+
+    let objects = player
+        .inventory
+        .iter()
+        .map(|(object, count)| {
+            if player.equipped == Some(object) {
+                message_with(
+                    "inventory.equipped",
+                    &[("objects", &object.display_count(count))],
+                )
+            } else {
+                object.display_count(count)
+            }
+        })
+        .collect::<Vec<String>>();
+
+    Ok(message_with(
+        "inventory.list",
+        &[("objects", &objects.join(", "))],
+    ))
+}
+
+/// Digs tunnels to new rooms connected to the current one, one per direction given, in order.
+/// With several directions each one gets its own report, and digging stops early, after saying
+/// why, once the player has nothing left to dig with (as when the sledge breaks) or dies
+fn dig<R: Rng + ?Sized>(
+    player: &mut Player,
+    dungeon: &mut Dungeon,
+    rng: &mut R,
+    config: &GenerationConfig,
+    args: &[&str],
+) -> CommandResult {
+    match args {
+        [] => Err(GameError::Usage("usage.dig")),
+        [direction] => dig_towards(player, dungeon, rng, config, direction),
+        directions => {
+            let mut reports = vec![];
+
+            for direction in directions {
+                match dig_towards(player, dungeon, rng, config, direction) {
+                    Err(error @ GameError::BareHands)
+                    | Err(error @ GameError::CannotDigWith(_)) => {
+                        reports.push(format!("{}: {}", direction, error));
+                        break;
+                    }
+                    Ok(report) => reports.push(format!("{}: {}", direction, report)),
+                    Err(error) => reports.push(format!("{}: {}", direction, error)),
+                }
+
+                if player.is_dead() {
+                    break;
+                }
+            }
+
+            Ok(reports.join("\n"))
+        }
+    }
+}
+
+/// Digs a tunnel to a new room in a single direction. Random numbers are drawn in a fixed order so
+/// that a seeded game always plays out the same: first the collapse roll, then, if the tunnel
+/// holds, one roll per object in `config.spawn_probabilities`, the trap roll, the hidden object
+/// roll (and the choice of the object if there is one), the flavor description, the gold vein roll
+/// (and its size if there is one) and, when digging down, the pit roll
+fn dig_towards<R: Rng + ?Sized>(
+    player: &mut Player,
+    dungeon: &mut Dungeon,
+    rng: &mut R,
+    config: &GenerationConfig,
+    direction: &str,
+) -> CommandResult {
+    let direction = Direction::from_string(direction).ok_or(GameError::UnknownDirection)?;
+
+    match player.equipped {
+        None => Err(GameError::BareHands),
+        Some(equipped) if !equipped.can_dig() => Err(GameError::CannotDigWith(equipped)),
+        Some(_) => {
+            let target_location = player
+                .location
+                .checked_add(direction.to_location())
+                .ok_or(GameError::EdgeOfWorld)?;
+
+            if dungeon.others.iter().any(|(at, _)| *at == target_location) {
+                return Err(GameError::RoomOccupied);
+            }
+            if dungeon.rooms.contains_key(&target_location) {
+                return Err(GameError::ExitAlreadyExists(direction));
+            }
+
+            if config
+                .max_rooms
+                .is_some_and(|max_rooms| dungeon.rooms.len() >= max_rooms)
+            {
+                return Err(GameError::RockTooHard);
+            }
+
+            let report = if rng.gen::<f32>() < config.collapse_probability {
+                player.damage(COLLAPSE_DAMAGE);
+
+                message_with(
+                    "dig.collapse",
+                    &[
+                        ("damage", &COLLAPSE_DAMAGE),
+                        ("health", &player.health),
+                        ("max_health", &player.max_health),
+                    ],
+                )
+            } else {
+                let mut room = Room::new()
+                    .dug_by_player()
+                    .with_random_objects(rng, config, Biome::at(target_location))
+                    .with_flavor(rng, Biome::at(target_location));
+                let vein = config.roll_gold_vein(rng, target_location.2);
+                room.objects.add(Object::Gold, vein);
+                let pit = direction == Direction::Down && rng.gen::<f32>() < PIT_PROBABILITY;
+                dungeon.rooms.insert(target_location, room.with_pit(pit));
+                player.rooms_dug += 1;
+
+                if vein > 0 {
+                    message_with("dig.vein", &[("direction", &direction)])
+                } else {
+                    message_with("dig.dug", &[("direction", &direction)])
+                }
+            };
+
+            if player.wear_sledge() {
+                Ok(format!("{}\n{}", report, message("dig.sledge-breaks")))
+            } else {
+                Ok(report)
+            }
+        }
+    }
+}
+
+/// Moves the player to an adjacent room. Going up needs a ladder, either carried or left on the
+/// floor, and going down into a pit needs a rope; both are not used up. Going through a locked
+/// passage needs a key instead, which is used up but leaves the passage unlocked for good
+fn goto(
+    player: &mut Player,
+    dungeon: &mut Dungeon,
+    direction: Direction,
+    verbosity: Verbosity,
+) -> CommandResult {
+    let room = dungeon
+        .rooms
+        .get(&player.location)
+        .ok_or(GameError::NoRoom)?;
+    if direction == Direction::Up && !player.inventory.can_climb() && !room.objects.can_climb() {
+        return Err(GameError::NeedLadder);
+    }
+    let locked = room.locked.contains(&direction);
+
+    let target_location = player
+        .location
+        .checked_add(direction.to_location())
+        .ok_or(GameError::EdgeOfWorld)?;
+    let target_room = dungeon
+        .rooms
+        .get(&target_location)
+        .ok_or(GameError::NoExit)?;
+
+    if direction == Direction::Down && target_room.pit && !player.inventory.contains(Object::Rope) {
+        return Err(GameError::NeedRope);
+    }
+
+    if locked {
+        if !player.inventory.contains(Object::Key) {
+            return Err(GameError::Locked);
+        }
+
+        player.inventory.remove(Object::Key, 1);
+        player.unequip_if_missing();
+        dungeon.unlock(player.location, direction);
+    }
+
+    player.trail.push(player.location);
+    player.location = target_location;
+    player.facing = Some(direction);
+    let first_visit = player.visited.insert(target_location);
+    player.moves += 1;
+
+    let verbosity = match verbosity {
+        Verbosity::Normal if first_visit => Verbosity::Verbose,
+        Verbosity::Normal => Verbosity::Brief,
+        verbosity => verbosity,
+    };
+
+    let mut report = String::new();
+    if locked {
+        report.push_str(&format!("{}\n", message("move.unlock")));
+    }
+
+    let room = dungeon
+        .rooms
+        .get_mut(&target_location)
+        .ok_or(GameError::NoRoom)?;
+    if room.hazard {
+        room.hazard = false;
+        player.damage(HAZARD_DAMAGE);
+
+        report.push_str(&format!(
+            "{}\n",
+            message_with(
+                "move.hazard",
+                &[
+                    ("damage", &HAZARD_DAMAGE),
+                    ("health", &player.health),
+                    ("max_health", &player.max_health),
+                ],
+            )
+        ));
+    }
+
+    report.push_str(&describe_room(player, dungeon, verbosity)?);
+    Ok(report)
+}
+
+/// Walks in the given direction or, without one, on in the direction of the last move
+fn walk(
+    player: &mut Player,
+    dungeon: &mut Dungeon,
+    verbosity: Verbosity,
+    args: &[&str],
+) -> CommandResult {
+    let direction = match args.first() {
+        Some(direction) => Direction::from_string(direction).ok_or(GameError::UnknownDirection)?,
+        None => player.facing.ok_or(GameError::NotFacing)?,
+    };
+
+    goto(player, dungeon, direction, verbosity)
+}
+
+/// Walks back to the room the player came from, following the same rules as any other move
+fn back(player: &mut Player, dungeon: &mut Dungeon, verbosity: Verbosity) -> CommandResult {
+    let direction = player
+        .trail
+        .last()
+        .and_then(|previous| player.location.direction_to(*previous))
+        .ok_or(GameError::NowhereToGoBack)?;
+
+    let description = goto(player, dungeon, direction, verbosity)?;
+    // Forget both the step just taken and the one it retraced
+    player.trail.truncate(player.trail.len() - 2);
+
+    Ok(description)
+}
+
+/// Climbs up to the room above, or down to the room below, the safe way into pits. Climbing up
+/// needs a ladder like walking up does
+fn climb(
+    player: &mut Player,
+    dungeon: &mut Dungeon,
+    verbosity: Verbosity,
+    args: &[&str],
+) -> CommandResult {
+    match args.first() {
+        None | Some(&"up") => {
+            let description = goto(player, dungeon, Direction::Up, verbosity)?;
+
+            Ok(format!("{}\n{}", message("climb.up"), description))
+        }
+        Some(&"down") => {
+            let description = goto(player, dungeon, Direction::Down, verbosity)?;
+
+            if player.inventory.contains(Object::Rope) {
+                Ok(format!("{}\n{}", message("climb.down-rope"), description))
+            } else {
+                Ok(format!("{}\n{}", message("climb.down"), description))
+            }
+        }
+        _ => Err(GameError::Usage("usage.climb")),
+    }
+}
+
+/// Checks that every room can be reached from the first one. Prize rooms are left out, as they
+/// can be set apart to be dug to
+fn check_rooms_connected(_: &Player, dungeon: &Dungeon) -> Option<String> {
+    let mut without_goals = dungeon.clone();
+    for goal in &dungeon.goals {
+        if *goal != Location(0, 0, 0) {
+            without_goals.rooms.remove(goal);
+        }
+    }
+
+    if without_goals.is_connected() {
+        None
+    } else {
+        Some(message("validate.disconnected"))
+    }
+}
+
+/// Checks that the player stands in a room
+fn check_player_room(player: &Player, dungeon: &Dungeon) -> Option<String> {
+    if dungeon.rooms.contains_key(&player.location) {
+        None
+    } else {
+        Some(message_with(
+            "validate.no-room",
+            &[("location", &format!("{:?}", player.location))],
+        ))
+    }
+}
+
+/// Checks that there is a prize room at each goal and that the prizes left can be walked to, or
+/// dug to if the player carries something to dig with
+fn check_goals(player: &Player, dungeon: &Dungeon) -> Option<String> {
+    if dungeon.goals.is_empty() {
+        return Some(message("validate.no-goals"));
+    }
+
+    let problems = dungeon
+        .goals
+        .iter()
+        .filter_map(|goal| {
+            if !dungeon.rooms.contains_key(goal) {
+                Some(message_with(
+                    "validate.missing-goal",
+                    &[("goal", &format!("{:?}", goal))],
+                ))
+            } else if !player.collected.contains(goal)
+                && !player.inventory.iter().any(|(object, _)| object.can_dig())
+                && dungeon.rooms.contains_key(&player.location)
+                && dungeon.path_to(player.location, *goal, player).is_none()
+            {
+                Some(message_with(
+                    "validate.unreachable-goal",
+                    &[("goal", &format!("{:?}", goal))],
+                ))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<String>>();
+
+    if problems.is_empty() {
+        None
+    } else {
+        Some(message_with(
+            "validate.goals",
+            &[("problems", &problems.join(", "))],
+        ))
+    }
+}
+
+/// Checks that no location is given as a goal twice. Rooms are keyed by their location, so they
+/// can't be duplicated
+fn check_unique_goals(_: &Player, dungeon: &Dungeon) -> Option<String> {
+    let unique = dungeon.goals.iter().collect::<HashSet<&Location>>();
+
+    if unique.len() == dungeon.goals.len() {
+        None
+    } else {
+        Some(message("validate.duplicate-goals"))
+    }
+}
+
+/// Checks that the equipped object, if any, is carried
+fn check_equipped(player: &Player, _: &Dungeon) -> Option<String> {
+    match player.equipped {
+        Some(object) if !player.inventory.contains(object) => {
+            Some(message_with("validate.equipped", &[("object", &object)]))
+        }
+        _ => None,
+    }
+}
+
+/// Runs every invariant check on the game and lists what's wrong. Only available when the game
+/// runs with `--debug`
+fn validate(player: &Player, dungeon: &Dungeon, debug: bool) -> CommandResult {
+    if !debug {
+        return Err(GameError::ValidateDisabled);
+    }
+
+    let checks: [fn(&Player, &Dungeon) -> Option<String>; 5] = [
+        check_rooms_connected,
+        check_player_room,
+        check_goals,
+        check_unique_goals,
+        check_equipped,
+    ];
+    let violations = checks
+        .iter()
+        .filter_map(|check| check(player, dungeon))
+        .collect::<Vec<String>>();
+
+    if violations.is_empty() {
+        Ok(message("validate.ok"))
+    } else {
+        Ok(violations.join("\n"))
+    }
+}
+
+/// Moves the player to arbitrary coordinates, creating an empty room there if needed. Only
+/// available when the game runs with `--debug`
+fn teleport(
+    player: &mut Player,
+    dungeon: &mut Dungeon,
+    debug: bool,
+    args: &[&str],
+) -> CommandResult {
+    if !debug {
+        return Err(GameError::TeleportDisabled);
+    }
+
+    let coordinates = args
+        .iter()
+        .map(|a| a.parse::<i32>())
+        .collect::<Result<Vec<i32>, _>>();
+
+    match coordinates.as_deref() {
+        Ok([x, y, z]) => {
+            let target_location = Location(*x, *y, *z);
+
+            dungeon
+                .rooms
+                .entry(target_location)
+                .or_insert_with(Room::new);
+            player.location = target_location;
+            player.visited.insert(target_location);
+            player.trail.clear();
+            player.facing = None;
+
+            look(player, dungeon)
+        }
+        _ => Err(GameError::Usage("usage.goto")),
+    }
+}
+
+/// Restores the dungeon and the player as they were before the last command that changed them
+fn undo(history: &mut History, player: &mut Player, dungeon: &mut Dungeon) -> CommandResult {
+    let (previous_dungeon, previous_player) = history.pop_back().ok_or(GameError::NothingToUndo)?;
+
+    *dungeon = previous_dungeon;
+    *player = previous_player;
+
+    Ok(message("undo.done"))
+}
+
+/// Fills an adjacent room with rock, removing it from the dungeon
+fn fill(player: &Player, dungeon: &mut Dungeon, args: &[&str]) -> CommandResult {
+    if args.is_empty() {
+        return Err(GameError::Usage("usage.fill"));
+    }
+
+    let direction = Direction::from_string(args[0]).ok_or(GameError::UnknownDirection)?;
+    let target_location = player
+        .location
+        .checked_add(direction.to_location())
+        .ok_or(GameError::NoExit)?;
+
+    dungeon.check_fillable(target_location)?;
+    dungeon.remove_room(target_location);
+
+    Ok(message_with("fill.done", &[("direction", &direction)]))
+}
+
+/// Turns on or off the automatic sealing of the empty dead ends the player leaves behind
+fn seal(auto_seal: &mut bool, args: &[&str]) -> CommandResult {
+    match args.first() {
+        Some(&"on") => {
+            *auto_seal = true;
+            Ok(message("seal.on"))
+        }
+        Some(&"off") => {
+            *auto_seal = false;
+            Ok(message("seal.off"))
+        }
+        _ => Err(GameError::Usage("usage.seal")),
+    }
+}
+
+/// Smallest rectangle on the x/y plane containing all the given locations, as
+/// `(min_x, max_x, min_y, max_y)`, cut down to `MAP_SPAN` rooms on each side around `center`
+fn map_bounds<'a>(
+    locations: impl Iterator<Item = &'a Location> + Clone,
+    center: Location,
+) -> (i32, i32, i32, i32) {
+    let (min_x, max_x) = clamp_span(
+        locations.clone().map(|l| l.0).min().unwrap_or(0),
+        locations.clone().map(|l| l.0).max().unwrap_or(0),
+        center.0,
+    );
+    let (min_y, max_y) = clamp_span(
+        locations.clone().map(|l| l.1).min().unwrap_or(0),
+        locations.map(|l| l.1).max().unwrap_or(0),
+        center.1,
+    );
+
+    (min_x, max_x, min_y, max_y)
+}
+
+/// Narrows the range from `min` to `max` to at most `MAP_SPAN` values, around `center` if it has
+/// to
+fn clamp_span(min: i32, max: i32, center: i32) -> (i32, i32) {
+    if max.abs_diff(min) < MAP_SPAN {
+        return (min, max);
+    }
+
+    let min = center.saturating_sub(MAP_SPAN as i32 / 2).max(min);
+    (min, min.saturating_add(MAP_SPAN as i32 - 1).min(max))
+}
+
+/// Renders a level of the dungeon as seen from above within the given bounds. The player is
+/// marked with `@`, rooms already visited with `#` and rooms that exist but were never entered
+/// with `?`. When `mark_shafts` is set, rooms with another room right above or below them are
+/// drawn as `|` so that vertical shafts line up across levels
+fn render_level(
+    player: &Player,
+    dungeon: &Dungeon,
+    z: i32,
+    bounds: (i32, i32, i32, i32),
+    mark_shafts: bool,
+) -> Vec<String> {
+    let (min_x, max_x, min_y, max_y) = bounds;
+
+    (min_y..=max_y)
+        .map(|y| {
+            (min_x..=max_x)
+                .map(|x| {
+                    let location = Location(x, y, z);
+                    let is_shaft = [Direction::Up, Direction::Down].iter().any(|direction| {
+                        location
+                            .checked_add(direction.to_location())
+                            .is_some_and(|other| dungeon.rooms.contains_key(&other))
+                    });
+
+                    if location == player.location {
+                        '@'
+                    } else if !dungeon.rooms.contains_key(&location) {
+                        '.'
+                    } else if mark_shafts && is_shaft {
+                        '|'
+                    } else if player.visited.contains(&location) {
+                        '#'
+                    } else {
+                        '?'
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Draws the current level of the dungeon
+fn map(player: &Player, dungeon: &Dungeon) -> CommandResult {
+    let z = player.location.2;
+    let bounds = map_bounds(dungeon.rooms.keys().filter(|l| l.2 == z), player.location);
+
+    let mut lines = vec![message_with("map.level", &[("level", &z)])];
+    lines.extend(render_level(player, dungeon, z, bounds, false));
+
+    Ok(lines.join("\n"))
+}
+
+/// Draws every level of the dungeon, from the top to the bottom, on a shared grid so that
+/// vertical shafts can be traced from one level to the next
+fn map_3d(player: &Player, dungeon: &Dungeon) -> CommandResult {
+    let bounds = map_bounds(dungeon.rooms.keys(), player.location);
+    let (min_z, max_z) = clamp_span(
+        dungeon.rooms.keys().map(|l| l.2).min().unwrap_or(0),
+        dungeon.rooms.keys().map(|l| l.2).max().unwrap_or(0),
+        player.location.2,
+    );
+
+    let mut lines = vec![];
+    for z in min_z..=max_z {
+        lines.push(message_with("map.level", &[("level", &z)]));
+        lines.extend(render_level(player, dungeon, z, bounds, true));
+    }
+
+    Ok(lines.join("\n"))
+}
+
+/// Tells the player where they are and how far the closest prize left is
+fn where_am_i(player: &Player, dungeon: &Dungeon) -> CommandResult {
+    match dungeon.next_goal(player) {
+        Some(goal) => Ok(message_with(
+            "where.goal",
+            &[
+                ("location", &format!("{:?}", player.location)),
+                ("biome", &Biome::at(player.location)),
+                ("distance", &player.location.manhattan_distance(goal)),
+            ],
+        )),
+        None => Ok(message_with(
+            "where.no-goal",
+            &[
+                ("location", &format!("{:?}", player.location)),
+                ("biome", &Biome::at(player.location)),
+            ],
+        )),
+    }
+}
+
+/// Tells the player the shortest way to the closest prize left through the rooms dug so far
+fn solve(player: &Player, dungeon: &Dungeon) -> CommandResult {
+    if !dungeon.rooms.contains_key(&player.location) {
+        return Err(GameError::NoRoom);
+    }
+    let goal = dungeon.next_goal(player).ok_or(GameError::NoPrizeLeft)?;
+
+    match dungeon.path_to(player.location, goal, player) {
+        Some(path) if path.is_empty() => Ok(message("solve.here")),
+        Some(path) => Ok(message_with(
+            "solve.path",
+            &[(
+                "path",
+                &path
+                    .iter()
+                    .map(|direction| color::direction(&direction.to_string()))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            )],
+        )),
+        None => Ok(message("solve.no-path")),
+    }
+}
+
+/// Tells the player which ways lead closer to the closest prize left
+fn hint(player: &Player, dungeon: &Dungeon) -> CommandResult {
+    let goal = dungeon.next_goal(player).ok_or(GameError::NoPrizeLeft)?;
+    let directions = player
+        .location
+        .directions_towards(goal)
+        .iter()
+        .map(|direction| match direction {
+            Direction::Up | Direction::Down => color::direction(&direction.to_string()),
+            _ => message_with(
+                "hint.direction",
+                &[("direction", &color::direction(&direction.to_string()))],
+            ),
+        })
+        .collect::<Vec<String>>();
+
+    match directions.split_last() {
+        None => Ok(message("hint.here")),
+        Some((last, [])) => Ok(message_with("hint.one", &[("direction", last)])),
+        Some((last, others)) => Ok(message_with(
+            "hint.several",
+            &[("directions", &others.join(", ")), ("last", last)],
+        )),
+    }
+}
+
+/// Summarizes the player's situation
+fn status(player: &Player) -> CommandResult {
+    let equipped = match player.equipped {
+        Some(object) => message_with("status.equipped", &[("object", &object)]),
+        None => message("status.nothing-equipped"),
+    };
+    let carrying = if player.inventory.is_empty() {
+        message("status.carrying-nothing")
+    } else {
+        message_with(
+            "status.carrying",
+            &[
+                ("objects", &player.inventory.list()),
+                ("weight", &player.inventory.weight()),
+            ],
+        )
+    };
+
+    let mut lines = vec![
+        message_with(
+            "status.location",
+            &[("location", &format!("{:?}", player.location))],
+        ),
+        message_with(
+            "status.health",
+            &[
+                ("health", &player.health),
+                ("max_health", &player.max_health),
+            ],
+        ),
+        equipped,
+        carrying,
+        message_with("status.moves", &[("moves", &player.moves)]),
+        message_with(
+            "status.hunger",
+            &[("hunger", &player.hunger), ("starving", &STARVING)],
+        ),
+    ];
+    if let Some(direction) = player.facing {
+        lines.push(message_with("status.facing", &[("direction", &direction)]));
+    }
+    if let Some(durability) = player.sledge_durability {
+        if player.inventory.contains(Object::Sledge) {
+            lines.push(message_with(
+                "status.sledge",
+                &[
+                    ("uses_left", &player.sledge_uses_left),
+                    ("durability", &durability),
+                ],
+            ));
+        }
+    }
+
+    Ok(lines.join("\n"))
+}
+
+/// Formats a duration as minutes and seconds, like `02:05`. Minutes keep counting past the hour
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Shows a few totals about the game so far
+fn stats(player: &Player, playtime: Duration) -> CommandResult {
+    Ok([
+        ("stats.rooms-dug", player.rooms_dug.to_string()),
+        ("stats.gold-collected", player.gold_collected.to_string()),
+        ("stats.moves", player.moves.to_string()),
+        ("stats.time-played", format_duration(playtime)),
+    ]
+    .iter()
+    .map(|(label, value)| format!("{:<16}{:>5}", message(label), value))
+    .collect::<Vec<String>>()
+    .join("\n"))
+}
+
+/// Equips an object, putting away the one equipped before (which stays in the inventory)
+fn equip(player: &mut Player, args: &[&str], objects: &CustomObjects) -> CommandResult {
+    if args.is_empty() {
+        return Err(GameError::Usage("usage.equip"));
+    }
+
+    match Object::from_string(args[0], objects) {
+        Some(object) if player.inventory.contains(object) => {
+            match player.equipped.replace(object) {
+                Some(previous) if previous != object => Ok(message_with(
+                    "equip.swapped",
+                    &[
+                        ("previous", &color::object(previous.name())),
+                        ("object", &color::object(object.name())),
+                    ],
+                )),
+                _ => Ok(message("equip.done")),
+            }
+        }
+        _ => Err(GameError::CannotEquip),
+    }
+}
+
+/// Unequips an object
+fn unequip(player: &mut Player) -> CommandResult {
+    if player.equipped.take().is_some() {
+        Ok(message("unequip.done"))
+    } else {
+        Err(GameError::NothingEquipped)
+    }
+}
+
+/// Parses the value given to a command line flag
+fn flag_value<T: FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("Missing value for \"{}\"", flag))?;
+
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value \"{}\" for \"{}\"", value, flag))
+}
+
+/// Turns a seed given on the command line into the number seeding the random numbers. Numbers are
+/// used as they are, anything else is hashed with 64-bit FNV-1a, which unlike the standard hasher
+/// gives the same result on every run and every machine
+fn seed_from_string(s: &str) -> u64 {
+    s.parse().unwrap_or_else(|_| {
+        s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+    })
+}
+
+/// Settings chosen on the command line when launching the game
+#[derive(Clone)]
+pub struct Options {
+    /// Enables debugging commands like teleporting (`--debug`)
+    debug: bool,
+    /// When to color the output (`--color=auto|always|never`)
+    color: ColorMode,
+    /// Health the player starts with (`--max-health N`)
+    max_health: i32,
+    /// Layout file to read the dungeon from (`--map FILE`)
+    map: Option<String>,
+    /// How rooms are generated (`--spawn OBJECT=PROBABILITY`, once per object to change,
+    /// `--max-rooms N`, `--collapse-chance PROBABILITY` and `--objects FILE`, which adds the
+    /// objects defined in the file and must come before any `--spawn` naming them).
+    /// `--difficulty easy|normal|hard` sets it together with `start` and `sledge_durability`,
+    /// and the flags after it can change them further
+    generation: GenerationConfig,
+    /// What the player starts with (`--start standard|empty|random|generous`)
+    start: StartingInventory,
+    /// File to append a transcript of the game to (`--log FILE`)
+    log: Option<String>,
+    /// File to read the commands from instead of the standard input (`--script FILE`)
+    script: Option<String>,
+    /// Locations of the prize rooms (`--goal X,Y,Z`, once per room); if none is given, the ones
+    /// of the layout or a single one at `DEFAULT_GOAL_LOCATION`
+    goals: Vec<Location>,
+    /// Number of rooms of a randomly generated dungeon to play in (`--generate N`)
+    generate: Option<usize>,
+    /// How many rooms a sledge can dig before breaking (`--sledge-durability N`), unlimited if
+    /// not given
+    sledge_durability: Option<u32>,
+    /// Seed of the random numbers (`--seed NUMBER|WORDS`), so that a game can be played again the
+    /// same way. Picked at random if not given
+    seed: Option<u64>,
+    /// Address to serve games on over TCP instead of playing one (`--serve ADDRESS`)
+    serve: Option<String>,
+    /// Whether all the players connected to the server share the same dungeon (`--shared`)
+    shared: bool,
+    /// Every how many moves the game is saved to `autosave_path` (`--autosave N`), never if 0
+    autosave: u32,
+    /// File the game is autosaved to. Left to the front end to pick, and never set when serving
+    /// games, so that the players don't overwrite each other's saves
+    autosave_path: Option<String>,
+    /// File keeping the best won games. Left to the front end to pick like `autosave_path`, and
+    /// never set when serving games
+    scores_path: Option<String>,
+    /// The messages shown, in the language loaded with `--lang FILE` and changed by the templates
+    /// loaded with `--templates FILE`, if any
+    messages: Messages,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            debug: false,
+            color: ColorMode::Auto,
+            max_health: 10,
+            map: None,
+            generation: GenerationConfig::default(),
+            start: StartingInventory::Standard,
+            log: None,
+            script: None,
+            goals: vec![],
+            generate: None,
+            sledge_durability: None,
+            seed: None,
+            serve: None,
+            shared: false,
+            autosave: 0,
+            autosave_path: None,
+            scores_path: None,
+            messages: Messages::default(),
+        }
+    }
+}
+
+impl Options {
+    /// When to color the output
+    pub fn color(&self) -> ColorMode {
+        self.color
+    }
+
+    /// File to append a transcript of the game to, if any
+    pub fn log(&self) -> Option<&str> {
+        self.log.as_deref()
+    }
+
+    /// Layout file to read the dungeon from, if any
+    pub fn map(&self) -> Option<&str> {
+        self.map.as_deref()
+    }
+
+    /// File to read the commands from instead of the standard input, if any
+    pub fn script(&self) -> Option<&str> {
+        self.script.as_deref()
+    }
+
+    /// Every how many moves the game is saved, never if 0
+    pub fn autosave(&self) -> u32 {
+        self.autosave
+    }
+
+    /// File the game is autosaved to, if any
+    pub fn autosave_path(&self) -> Option<&str> {
+        self.autosave_path.as_deref()
+    }
+
+    /// Sets the file the game is autosaved to
+    pub fn set_autosave_path(&mut self, path: &str) {
+        self.autosave_path = Some(path.to_string());
+    }
+
+    /// File keeping the best won games, if any
+    pub fn scores_path(&self) -> Option<&str> {
+        self.scores_path.as_deref()
+    }
+
+    /// Sets the file keeping the best won games
+    pub fn set_scores_path(&mut self, path: &str) {
+        self.scores_path = Some(path.to_string());
+    }
+
+    /// The messages shown
+    pub fn messages(&self) -> &Messages {
+        &self.messages
+    }
+
+    /// Address to serve games on over TCP, if any
+    pub fn serve(&self) -> Option<&str> {
+        self.serve.as_deref()
+    }
+
+    /// Reads the options from the command line arguments, program name excluded
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--debug" => options.debug = true,
+                "--shared" => options.shared = true,
+                "--map" => options.map = Some(flag_value(&arg, args.next())?),
+                "--log" => options.log = Some(flag_value(&arg, args.next())?),
+                "--script" => options.script = Some(flag_value(&arg, args.next())?),
+                "--serve" => options.serve = Some(flag_value(&arg, args.next())?),
+                "--autosave" => options.autosave = flag_value(&arg, args.next())?,
+                "--sledge-durability" => {
+                    let durability = flag_value(&arg, args.next())?;
+                    if durability == 0 {
+                        return Err("The sledge durability must be positive".to_string());
+                    }
+                    options.sledge_durability = Some(durability);
+                }
+                "--seed" => {
+                    options.seed = Some(seed_from_string(&flag_value::<String>(&arg, args.next())?))
+                }
+                "--generate" => options.generate = Some(flag_value(&arg, args.next())?),
+                "--goal" => {
+                    let goal = flag_value(&arg, args.next())?;
+                    if goal == Location(0, 0, 0) {
+                        return Err("The prize can't be in the first room".to_string());
+                    }
+                    if !options.goals.contains(&goal) {
+                        options.goals.push(goal);
+                    }
+                }
+                "--difficulty" => {
+                    let difficulty = flag_value::<String>(&arg, args.next())?;
+                    Difficulty::from_string(&difficulty)
+                        .ok_or_else(|| format!("Invalid difficulty \"{}\"", difficulty))?
+                        .apply(&mut options)
+                }
+                "--start" => {
+                    let start = flag_value::<String>(&arg, args.next())?;
+                    options.start = StartingInventory::from_string(&start)
+                        .ok_or_else(|| format!("Invalid starting inventory \"{}\"", start))?
+                }
+                "--collapse-chance" => {
+                    options.generation.collapse_probability = flag_value(&arg, args.next())?;
+                    if !(0.0..=1.0).contains(&options.generation.collapse_probability) {
+                        return Err("The collapse chance must be between 0 and 1".to_string());
+                    }
+                }
+                "--max-rooms" => {
+                    options.generation.max_rooms = Some(flag_value(&arg, args.next())?)
+                }
+                "--objects" => options
+                    .generation
+                    .load_objects(&flag_value::<String>(&arg, args.next())?)?,
+                "--lang" => options
+                    .messages
+                    .load_language(&flag_value::<String>(&arg, args.next())?)?,
+                "--templates" => options
+                    .messages
+                    .load_templates(&flag_value::<String>(&arg, args.next())?)?,
+                "--spawn" => options
+                    .generation
+                    .set_spawn_probability(&flag_value::<String>(&arg, args.next())?)?,
+                "--max-health" => {
+                    options.max_health = flag_value(&arg, args.next())?;
+                    if options.max_health <= 0 {
+                        return Err("The maximum health must be positive".to_string());
+                    }
+                }
+                _ if arg.starts_with("--color=") => {
+                    options.color = ColorMode::from_string(&arg["--color=".len()..])
+                        .ok_or_else(|| format!("Invalid color mode in \"{}\"", arg))?
+                }
+                _ => return Err(format!("Unknown option \"{}\"", arg)),
+            }
+        }
+
+        if options.generate.is_some() && (options.map.is_some() || !options.goals.is_empty()) {
+            return Err("A generated dungeon can't be used with --map or --goal".to_string());
+        }
+
+        if options.shared && options.serve.is_none() {
+            return Err("A shared dungeon can only be used with --serve".to_string());
+        }
+
+        Ok(options)
+    }
+}
+
+/// Appends a command and its result to the log, each line prefixed by the time in seconds since
+/// the Unix epoch. The log is flushed right away so that it's complete even after a crash
+fn log_command(log: &mut File, input: &str, result: &str) -> io::Result<()> {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let timestamp = format!("[{}.{:03}]", elapsed.as_secs(), elapsed.subsec_millis());
+
+    writeln!(log, "{} > {}", timestamp, input)?;
+    for line in result.lines() {
+        writeln!(log, "{} {}", timestamp, line)?;
+    }
+
+    log.flush()
+}
+
+/// Milestones announced to the player the first time they reach them
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+enum Achievement {
+    FirstDig,
+    FirstGold,
+    DeepDown,
+    LongWalk,
+    Winner,
+}
+
+impl Display for Achievement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Achievement::FirstDig => write!(f, "{}", message("achievement.first-dig")),
+            Achievement::FirstGold => write!(f, "{}", message("achievement.first-gold")),
+            Achievement::DeepDown => write!(
+                f,
+                "{}",
+                message_with("achievement.deep-down", &[("depth", &ACHIEVEMENT_DEPTH)])
+            ),
+            Achievement::LongWalk => write!(
+                f,
+                "{}",
+                message_with("achievement.long-walk", &[("moves", &ACHIEVEMENT_MOVES)])
+            ),
+            Achievement::Winner => write!(f, "{}", message("achievement.winner")),
+        }
+    }
+}
+
+impl Achievement {
+    /// The word the achievement is saved as
+    fn name(self) -> &'static str {
+        match self {
+            Achievement::FirstDig => "first-dig",
+            Achievement::FirstGold => "first-gold",
+            Achievement::DeepDown => "deep-down",
+            Achievement::LongWalk => "long-walk",
+            Achievement::Winner => "winner",
+        }
+    }
+
+    /// Tries to parse a string to an achievement, like `"winner"` to `Achievement::Winner`
+    fn from_string(s: &str) -> Option<Achievement> {
+        match s {
+            "first-dig" => Some(Achievement::FirstDig),
+            "first-gold" => Some(Achievement::FirstGold),
+            "deep-down" => Some(Achievement::DeepDown),
+            "long-walk" => Some(Achievement::LongWalk),
+            "winner" => Some(Achievement::Winner),
+            _ => None,
+        }
+    }
+}
+
+/// All the achievements the player deserves in the current state of the game, whether they were
+/// already announced or not
+fn earned_achievements(player: &Player, won: bool) -> Vec<Achievement> {
+    [
+        (Achievement::FirstDig, player.rooms_dug > 0),
+        (Achievement::FirstGold, player.gold_collected > 0),
+        (
+            Achievement::DeepDown,
+            player.location.2 >= ACHIEVEMENT_DEPTH,
+        ),
+        (Achievement::LongWalk, player.moves >= ACHIEVEMENT_MOVES),
+        (Achievement::Winner, won),
+    ]
+    .iter()
+    .filter(|(_, earned)| *earned)
+    .map(|(achievement, _)| *achievement)
+    .collect()
+}
+
+/// How rooms are described when walking in them
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Verbosity {
+    /// Fully the first time a room is entered, briefly afterwards
+    Normal,
+    /// Everything, like `look`
+    Verbose,
+    /// Only the name (or coordinates) and the exits
+    Brief,
+}
+
+impl Verbosity {
+    /// The command switching to this verbosity, which it is also saved as
+    fn name(self) -> &'static str {
+        match self {
+            Verbosity::Normal => "normal",
+            Verbosity::Verbose => "verbose",
+            Verbosity::Brief => "brief",
+        }
+    }
+
+    /// Tries to parse a string to a verbosity, like `"brief"` to `Verbosity::Brief`
+    fn from_string(s: &str) -> Option<Verbosity> {
+        match s {
+            "normal" => Some(Verbosity::Normal),
+            "verbose" => Some(Verbosity::Verbose),
+            "brief" => Some(Verbosity::Brief),
+            _ => None,
+        }
+    }
+}
+
+/// The whole state of a running game
+pub struct Game {
+    /// Aliases the player can use to issue commands
+    command_aliases: CommandAliases,
+    /// The dungeon being dug
+    dungeon: Dungeon,
+    /// The one exploring the dungeon
+    player: Player,
+    /// Source of randomness for the dungeon generation. Any generator can be plugged in, like a
+    /// seeded one to replay a game
+    rng: Box<dyn RngCore>,
+    /// How new rooms are generated
+    generation: GenerationConfig,
+    /// Whether the empty dead ends dug by the player are filled once they leave them
+    auto_seal: bool,
+    /// How much is told about the rooms the player walks in
+    verbosity: Verbosity,
+    /// Whether debugging commands are enabled
+    debug: bool,
+    /// When the clock started counting the time played in this session
+    started: Instant,
+    /// Time played in earlier sessions of the same game
+    played: Duration,
+    /// Command lines recorded with `record`, by name
+    macros: HashMap<String, Vec<String>>,
+    /// The name of the macro being recorded and the lines recorded so far, while recording
+    recording: Option<(String, Vec<String>)>,
+    /// Macros being played, the innermost last, to stop one from playing itself
+    playing: Vec<String>,
+    /// States to go back to with `undo`
+    history: History,
+    /// Text shown when waiting for a command, nothing if empty
+    prompt: String,
+    /// Whether the game has ended, making the main loop stop
+    over: bool,
+    /// Whether the player reached the prize
+    won: bool,
+    /// Achievements already earned, so that each one is announced once
+    achievements: HashSet<Achievement>,
+    /// Where to write a transcript of the commands and their results, if anywhere
+    log: Option<File>,
+    /// Whether the dungeon is shared with other players. Commands can't be undone then, as that
+    /// would also undo what the others did
+    shared: bool,
+    /// Which of the players sharing the dungeon this is, counting from 1
+    number: usize,
+    /// Objects given away with `give` and the name of whom to, for the server to hand them over
+    given: Vec<(String, Object, u32)>,
+    /// Every how many moves the game is saved to the `autosave_path` of the options, never if 0
+    autosave: u32,
+    /// Whether `restart` was asked for, so that the next line answers whether to go on with it
+    confirming_restart: bool,
+    /// What the game was set up with, to set up a new one on `restart`
+    options: Options,
+    /// Seed the random numbers started from, picked at random if not given in the options
+    seed: u64,
+}
+
+impl Game {
+    /// A new game set up as the options say. Layout and log files are not read: see `load_map`
+    /// and `open_log`
+    pub fn new(options: &Options) -> Self {
+        let _speaking = options.messages.speak();
+        let seed = options.seed.unwrap_or_else(|| StdRng::from_entropy().gen());
+        let mut rng = StdRng::seed_from_u64(seed);
+        let inventory = options.start.roll(&mut rng, &options.generation);
+        let dungeon = match options.generate {
+            Some(size) => Dungeon::generate(&mut rng, &options.generation, size),
+            None if options.goals.is_empty() => Dungeon::new(&[DEFAULT_GOAL_LOCATION]),
+            None => Dungeon::new(&options.goals),
+        };
+
+        Game {
+            command_aliases: default_aliases(),
+            dungeon,
+            player: Player::new(options.max_health, inventory, options.sledge_durability),
+            rng: Box::new(rng),
+            generation: options.generation.clone(),
+            auto_seal: false,
+            verbosity: Verbosity::Normal,
+            debug: options.debug,
+            started: Instant::now(),
+            played: Duration::default(),
+            macros: HashMap::new(),
+            recording: None,
+            playing: vec![],
+            history: VecDeque::new(),
+            prompt: "> ".to_string(),
+            over: false,
+            won: false,
+            achievements: HashSet::new(),
+            log: None,
+            shared: false,
+            number: 1,
+            given: vec![],
+            autosave: options.autosave,
+            confirming_restart: false,
+            options: options.clone(),
+            seed,
+        }
+    }
+
+    /// Points earned for winning the game: gold carried out and health left
+    fn score(&self) -> u32 {
+        self.player.inventory.count(Object::Gold) * GOLD_SCORE
+            + self.player.health.max(0) as u32 * HEALTH_SCORE
+    }
+
+    /// Adds the won game to the high scores, if the options name a file for them, telling the
+    /// score and whether it made it into them. Failing to write them is only a warning
+    fn record_score(&self) -> String {
+        let score = scores::Score {
+            score: self.score(),
+            seed: self.seed,
+            moves: self.player.moves,
+        };
+        let text = message_with("score.score", &[("score", &score.score)]);
+        let path = match &self.options.scores_path {
+            Some(path) => path,
+            None => return text,
+        };
+
+        match scores::record(path, score) {
+            Ok(true) => format!("{} {}", text, message("score.high-score")),
+            Ok(false) => text,
+            Err(error) => {
+                eprintln!(
+                    "{}",
+                    message_with("score.cannot-write", &[("path", path), ("error", &error)])
+                );
+                text
+            }
+        }
+    }
+
+    /// The wall-clock time spent playing the game, over all its sessions
+    fn playtime(&self) -> Duration {
+        self.played + self.started.elapsed()
+    }
+
+    /// Asks whether to really throw the game away, which the next line answers
+    fn ask_restart(&mut self) -> CommandResult {
+        if self.shared {
+            return Err(GameError::SharedRestart);
+        }
+
+        self.confirming_restart = true;
+        Ok(message("restart.ask"))
+    }
+
+    /// Starts a new game set up like this one, keeping the aliases, the macros and the settings
+    /// changed while playing
+    fn restart(&mut self) -> String {
+        let mut fresh = Game::new(&self.options);
+        let mut report = String::new();
+        if let Some(path) = &self.options.map {
+            if let Err(error) = fresh.load_map(path) {
+                report = format!("{}\n{}\n", error, message("restart.default-dungeon"));
+            }
+        }
+
+        *self = Game {
+            command_aliases: std::mem::take(&mut self.command_aliases),
+            macros: std::mem::take(&mut self.macros),
+            verbosity: self.verbosity,
+            auto_seal: self.auto_seal,
+            prompt: std::mem::take(&mut self.prompt),
+            log: self.log.take(),
+            ..fresh
+        };
+        report.push_str(&self.intro());
+        report
+    }
+
+    /// Starts recording the command lines that follow under the given name
+    fn record(&mut self, args: &[&str]) -> CommandResult {
+        let name = match args {
+            [name] => name.to_string(),
+            _ => return Err(GameError::Usage("usage.record")),
+        };
+        if self.recording.is_some() {
+            return Err(GameError::AlreadyRecording);
+        }
+
+        self.recording = Some((name.clone(), vec![]));
+        Ok(message_with("record.start", &[("name", &name)]))
+    }
+
+    /// Stops recording, keeping what was recorded for `play`
+    fn end_recording(&mut self) -> CommandResult {
+        let (name, lines) = self.recording.take().ok_or(GameError::NotRecording)?;
+        let count = lines.len();
+        self.macros.insert(name.clone(), lines);
+
+        Ok(message_with(
+            if count == 1 {
+                "record.one"
+            } else {
+                "record.many"
+            },
+            &[("count", &count), ("name", &name)],
+        ))
+    }
+
+    /// Runs the lines recorded under a name one after the other, stopping if the game ends
+    fn play(&mut self, args: &[&str]) -> CommandResult {
+        let name = match args {
+            [name] => name.to_string(),
+            _ => return Err(GameError::Usage("usage.play")),
+        };
+        if self.playing.contains(&name) {
+            return Err(GameError::MacroLoop(name));
+        }
+        let lines = self
+            .macros
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| GameError::UnknownMacro(name.clone()))?;
+
+        self.playing.push(name);
+        let mut outputs = vec![];
+        for line in lines {
+            outputs.push(self.step(&line));
+            if self.over {
+                break;
+            }
+        }
+        self.playing.pop();
+
+        Ok(outputs.join("\n"))
+    }
+
+    /// Walks one room in `direction`
+    fn go(&mut self, direction: Direction) -> CommandResult {
+        goto(
+            &mut self.player,
+            &mut self.dungeon,
+            direction,
+            self.verbosity,
+        )
+    }
+
+    /// The name the other players know this one by: the one picked with `rename`, if any, or
+    /// else one made of the player's number
+    fn name(&self) -> String {
+        match &self.player.name {
+            Some(name) => name.clone(),
+            None => message_with("player.default-name", &[("number", &self.number)]),
+        }
+    }
+
+    /// Tells the player who they are to the others
+    fn whoami(&self) -> String {
+        message_with(
+            "whoami",
+            &[("name", &self.name()), ("number", &self.number)],
+        )
+    }
+
+    /// Hands some objects over to another player in the same room, named first. With a count,
+    /// gives up to that many objects
+    fn give(&mut self, args: &[&str]) -> CommandResult {
+        let (recipient, rest) = args.split_first().ok_or(GameError::Usage(GIVE_USAGE))?;
+        let (count, object_name) = parse_quantity(rest, &self.generation.objects, GIVE_USAGE)?;
+        let location = self.player.location;
+        let recipient = self
+            .dungeon
+            .others
+            .iter()
+            .find(|(at, name)| *at == location && name.to_lowercase() == *recipient)
+            .map(|(_, name)| name.clone())
+            .ok_or_else(|| GameError::NobodyToGiveTo(recipient.to_string()))?;
+
+        let object = match Object::from_string(object_name, &self.generation.objects) {
+            Some(object) if self.player.inventory.contains(object) => object,
+            _ => return Err(GameError::NotInInventory),
+        };
+        let given = self
+            .player
+            .inventory
+            .remove(object, count.unwrap_or(u32::MAX));
+        self.given.push((recipient.clone(), object, given));
+
+        let report = message_with(
+            "give.done",
+            &[
+                ("objects", &object.display_count(given)),
+                ("name", &recipient),
+            ],
+        );
+        if self.player.unequip_if_missing() {
+            Ok(format!("{}{}", report, message("drop.unequipped")))
+        } else {
+            Ok(report)
+        }
+    }
+
+    /// Switches how rooms are described and tells the player what changed
+    fn set_verbosity(&mut self, verbosity: Verbosity) -> CommandResult {
+        self.verbosity = verbosity;
+        Ok(message(match verbosity {
+            Verbosity::Brief => "verbosity.brief",
+            Verbosity::Normal => "verbosity.normal",
+            Verbosity::Verbose => "verbosity.verbose",
+        }))
+    }
+
+    /// Parses a line of input, runs the command it contains and returns the text to show to the
+    /// player, which is empty if the line was blank or a comment starting with `#`
+    pub fn step(&mut self, input: &str) -> String {
+        let _speaking = self.options.messages.speak();
+
+        if input.trim_start().starts_with('#') {
+            return String::new();
+        }
+
+        if self.confirming_restart {
+            self.confirming_restart = false;
+            return match input.trim().to_lowercase().as_str() {
+                "y" | "yes" => self.restart(),
+                _ => message("restart.cancel"),
+            };
+        }
+
+        let raw_tokens = tokenize(input.trim());
+        let tokens = raw_tokens
+            .iter()
+            .map(|t| t.to_lowercase())
+            .collect::<Vec<String>>();
+
+        let splitted = tokens.iter().map(String::as_str).collect::<Vec<&str>>();
+        let raw_args = raw_tokens
+            .iter()
+            .skip(1)
+            .map(String::as_str)
+            .collect::<Vec<&str>>();
+
+        if splitted.is_empty() {
+            return String::new();
+        }
+
+        let command = find_command(splitted[0], &self.command_aliases);
+        if let Some((_, lines)) = &mut self.recording {
+            if command != Some(Command::End) {
+                lines.push(input.trim().to_string());
+                return message_with("record.line", &[("line", &input.trim())]);
+            }
+        }
+
+        let snapshot = match command {
+            Some(command) if command.mutates() && !self.shared => {
+                Some((self.dungeon.clone(), self.player.clone()))
+            }
+            _ => None,
+        };
+
+        let previous_location = self.player.location;
+        let previous_moves = self.player.moves;
+
+        let mut result = match command {
+            Some(command) => command.handler()(self, command, &splitted[1..], &raw_args),
+            None => Err(GameError::UnknownCommand(closest_alias(
+                splitted[0],
+                &self.command_aliases,
+            ))),
+        };
+
+        // Each line played went through all of what follows already
+        if command == Some(Command::Play) {
+            return result.unwrap_or_else(|error| error.to_string());
+        }
+
+        if self.auto_seal
+            && self.player.location != previous_location
+            && self.dungeon.can_seal(previous_location)
+        {
+            self.dungeon.remove_room(previous_location);
+            result = result.map(|output| format!("{}\n{}", output, message("seal.collapse")));
+        }
+
+        if let Some(feeling) = self
+            .player
+            .grow_hungry(self.player.moves.saturating_sub(previous_moves))
+        {
+            result = result.map(|output| format!("{}\n{}", output, feeling));
+        }
+
+        if self.player.is_dead() {
+            self.over = true;
+            result = Ok(format!(
+                "{}\n{}",
+                result.unwrap_or_else(|error| error.to_string()),
+                message("game.death")
+            ));
+        } else if self.dungeon.next_goal(&self.player).is_none() {
+            self.over = true;
+            self.won = true;
+            result = Ok(format!(
+                "{}\n{}\n{}",
+                result.unwrap_or_else(|error| error.to_string()),
+                message_with(
+                    "game.victory",
+                    &[
+                        ("moves", &self.player.moves),
+                        ("time", &format_duration(self.playtime())),
+                    ],
+                ),
+                self.record_score()
+            ));
+        }
+
+        if let Ok(output) = &mut result {
+            for achievement in earned_achievements(&self.player, self.won) {
+                if self.achievements.insert(achievement) {
+                    output.push('\n');
+                    output.push_str(&message_with(
+                        "achievement.unlocked",
+                        &[("achievement", &achievement)],
+                    ));
+                }
+            }
+        }
+
+        if let Some(path) = &self.options.autosave_path {
+            if self.autosave > 0
+                && self.player.moves / self.autosave > previous_moves / self.autosave
+                && !self.over
+            {
+                if let Err(error) = self.save(path) {
+                    eprintln!(
+                        "{}",
+                        message_with("autosave.cannot-save", &[("path", path), ("error", &error)])
+                    );
+                }
+            }
+        }
+
+        if let (Some(snapshot), Ok(_)) = (snapshot, &result) {
+            if self.history.len() == UNDO_HISTORY_SIZE {
+                self.history.pop_front();
+            }
+            self.history.push_back(snapshot);
+        }
+
+        result.unwrap_or_else(|error| error.to_string())
+    }
+
+    /// What the player is told when the game starts: where the prizes are and how to play
+    fn intro(&self) -> String {
+        let goals = self
+            .dungeon
+            .goals
+            .iter()
+            .map(|goal| format!("{},{},{}", goal.0, goal.1, goal.2))
+            .collect::<Vec<String>>();
+
+        format!(
+            "{}\n\n{}",
+            message_with(
+                if goals.len() == 1 {
+                    "intro.one"
+                } else {
+                    "intro.many"
+                },
+                &[("goals", &goals.join(message("intro.and").as_str()))],
+            ),
+            help(&self.command_aliases, &[]).unwrap_or_default()
+        )
+    }
+
+    /// Whether the game has ended, won or lost
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    /// The words that can complete the last word of a line being typed, for a line editor to offer
+    pub fn completions(&self, line: &str) -> Vec<String> {
+        complete::candidates(self, line)
+    }
+
+    /// The text shown when waiting for a command
+    pub fn prompt(&self) -> &str {
+        &self.prompt
+    }
+
+    /// Changes the text shown when waiting for a command. An empty prompt shows nothing
+    pub fn set_prompt(&mut self, prompt: &str) {
+        self.prompt = prompt.to_string();
+    }
+
+    /// Starts appending a transcript of the game to the file at `path`, creating it if needed
+    pub fn open_log(&mut self, path: &str) -> io::Result<()> {
+        self.log = Some(OpenOptions::new().create(true).append(true).open(path)?);
+        Ok(())
+    }
+
+    /// Writes the whole state of the game to the file at `path`, to `resume` it later
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        std::fs::write(path, save::write(self))
+    }
+
+    /// A game saved to the file at `path`, set up otherwise as the options say
+    pub fn resume(path: &str, options: &Options) -> Result<Game, String> {
+        let _speaking = options.messages.speak();
+        let saved = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+
+        save::read(&saved, options).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Replaces the dungeon with the one read from the layout file at `path`, its prize rooms
+    /// being the ones given with `--goal` if any, or else the ones of the layout. Layouts whose
+    /// prize rooms can't be walked to are refused
+    pub fn load_map(&mut self, path: &str) -> Result<(), String> {
+        let _speaking = self.options.messages.speak();
+        let mut dungeon = Dungeon::from_file(path, &self.generation.objects)?;
+        if !self.options.goals.is_empty() {
+            dungeon.goals = self.options.goals.clone();
+        }
+        dungeon
+            .check_goals()
+            .map_err(|e| format!("{}: {}", path, e))?;
+
+        self.dungeon = dungeon;
+        Ok(())
+    }
+
+    /// Plays the game reading commands line by line from `input` and writing the results to
+    /// `output`, until the input is exhausted. The prompt, if any, is written before each line is
+    /// read
+    pub fn run<R: LineSource, W: Write>(&mut self, mut input: R, output: &mut W) -> io::Result<()> {
+        let _speaking = self.options.messages.speak();
+        writeln!(output, "{}", self.intro())?;
+
+        loop {
+            if !self.prompt.is_empty() {
+                write!(output, "{}", self.prompt)?;
+                output.flush()?;
+            }
+
+            let mut line = String::new();
+            if input.read_line(self, &mut line)? == 0 {
+                if !self.prompt.is_empty() {
+                    writeln!(output)?;
+                }
+                writeln!(output, "{}", message("game.goodbye"))?;
+                return Ok(());
+            }
+
+            let result = self.step(&line);
+            if !result.is_empty() {
+                writeln!(output, "{}", result)?;
+            }
+
+            if let Some(log) = &mut self.log {
+                if let Err(error) = log_command(log, line.trim(), &result) {
+                    eprintln!("{}", message_with("log.cannot-write", &[("error", &error)]));
+                    self.log = None;
+                }
+            }
+
+            if self.over {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Where `Game::run` reads the lines typed by the player from. Any `BufRead` will do; a line editor
+/// can also look at the game waiting for the line, to complete what is being typed
+pub trait LineSource {
+    /// Appends the next line, with its line feed, to `line` and returns how many bytes it took,
+    /// 0 meaning that the input is exhausted
+    fn read_line(&mut self, game: &Game, line: &mut String) -> io::Result<usize>;
+}
+
+impl<R: BufRead> LineSource for R {
+    fn read_line(&mut self, _: &Game, line: &mut String) -> io::Result<usize> {
+        BufRead::read_line(self, line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A player with nothing in their hands standing in the first room of a fresh dungeon
+    fn start() -> (Player, Dungeon) {
+        (
+            Player::new(10, Inventory::default(), None),
+            Dungeon::new(&[DEFAULT_GOAL_LOCATION]),
+        )
+    }
+
+    #[test]
+    fn a_room_alone_has_no_exits() {
+        let (_, dungeon) = start();
+
+        assert!(dungeon.exits_for_room(Location(0, 0, 0)).is_empty());
+    }
+
+    #[test]
+    fn a_room_next_to_another_has_one_exit() {
+        let (_, mut dungeon) = start();
+        dungeon.rooms.insert(Location(0, 0, 1), Room::new());
+
+        assert_eq!(
+            dungeon.exits_for_room(Location(0, 0, 0)),
+            vec![Direction::Down]
+        );
+        assert_eq!(
+            dungeon.exits_for_room(Location(0, 0, 1)),
+            vec![Direction::Up]
+        );
+    }
+
+    #[test]
+    fn exits_are_listed_in_the_order_of_the_direction_mapping() {
+        let (_, mut dungeon) = start();
+        for (offset, _) in DIRECTION_MAPPING.iter().rev() {
+            dungeon.rooms.insert(*offset, Room::new());
+        }
+
+        assert_eq!(
+            dungeon.exits_for_room(Location(0, 0, 0)),
+            vec![
+                Direction::North,
+                Direction::South,
+                Direction::West,
+                Direction::East,
+                Direction::Down,
+                Direction::Up,
+            ]
+        );
+    }
+
+    #[test]
+    fn there_is_nothing_to_take_in_an_empty_room() {
+        let (mut player, mut dungeon) = start();
+        dungeon.rooms.get_mut(&Location(0, 0, 0)).unwrap().objects = Inventory::default();
+
+        assert_eq!(
+            take(
+                &mut player,
+                &mut dungeon,
+                &["sledge"],
+                &CustomObjects::default()
+            )
+            .unwrap_err()
+            .to_string(),
+            "There is nothing to take here"
+        );
+        assert!(player.inventory.is_empty());
+    }
+
+    /// Takes `args` in a first room where only five gold lie
+    fn take_from_five_gold(args: &[&str]) -> (CommandResult, Player, Dungeon) {
+        let (mut player, mut dungeon) = start();
+        let objects = &mut dungeon.rooms.get_mut(&Location(0, 0, 0)).unwrap().objects;
+        *objects = Inventory::default();
+        objects.add(Object::Gold, 5);
+
+        let result = take(&mut player, &mut dungeon, args, &CustomObjects::default());
+        (result, player, dungeon)
+    }
+
+    #[test]
+    fn taking_a_count_moves_that_many_objects() {
+        let (result, player, dungeon) = take_from_five_gold(&["3", "gold"]);
+
+        assert!(result.is_ok());
+        assert_eq!(player.inventory.count(Object::Gold), 3);
+        assert_eq!(
+            dungeon.rooms[&Location(0, 0, 0)]
+                .objects
+                .count(Object::Gold),
+            2
+        );
+    }
+
+    #[test]
+    fn taking_exactly_what_is_there_empties_the_pile() {
+        let (result, player, dungeon) = take_from_five_gold(&["5", "gold"]);
+
+        assert!(result.is_ok());
+        assert_eq!(player.inventory.count(Object::Gold), 5);
+        assert!(dungeon.rooms[&Location(0, 0, 0)].objects.is_empty());
+    }
+
+    #[test]
+    fn taking_more_than_there_is_takes_what_there_is() {
+        let (result, player, dungeon) = take_from_five_gold(&["8", "gold"]);
+
+        assert!(result.unwrap().contains('5'));
+        assert_eq!(player.inventory.count(Object::Gold), 5);
+        assert!(dungeon.rooms[&Location(0, 0, 0)].objects.is_empty());
+    }
+
+    #[test]
+    fn taking_an_invalid_count_tells_how_to_take() {
+        for count in &["-2", "abc", "0"] {
+            let (result, player, dungeon) = take_from_five_gold(&[count, "gold"]);
+
+            assert_eq!(
+                result.unwrap_err().to_string(),
+                "To take something: take [COUNT] OBJECT|all"
+            );
+            assert!(player.inventory.is_empty());
+            assert_eq!(
+                dungeon.rooms[&Location(0, 0, 0)]
+                    .objects
+                    .count(Object::Gold),
+                5
+            );
+        }
+    }
+
+    /// A JSON value, enough of it to read back what `to_json` writes
+    #[derive(Debug, PartialEq)]
+    enum Json {
+        Null,
+        Number(i64),
+        String(String),
+        Array(Vec<Json>),
+        Object(Vec<(String, Json)>),
+    }
+
+    impl Json {
+        /// The value of `key`, if this is an object holding it
+        fn get(&self, key: &str) -> Option<&Json> {
+            match self {
+                Json::Object(fields) => fields
+                    .iter()
+                    .find(|(name, _)| name == key)
+                    .map(|(_, value)| value),
+                _ => None,
+            }
+        }
+    }
+
+    /// Parses a whole JSON document, failing on anything malformed or left over
+    fn parse_json(text: &str) -> Json {
+        let mut chars = text.chars().peekable();
+        let value = parse_value(&mut chars);
+        assert_eq!(chars.next(), None, "trailing text after the JSON value");
+        value
+    }
+
+    /// Parses the value starting at the next character
+    fn parse_value(chars: &mut std::iter::Peekable<std::str::Chars>) -> Json {
+        match chars.peek().copied() {
+            Some('n') => {
+                assert_eq!(chars.take(4).collect::<String>(), "null");
+                Json::Null
+            }
+            Some('"') => Json::String(parse_string(chars)),
+            Some('[') => {
+                chars.next();
+                let mut items = vec![];
+                while chars.peek() != Some(&']') {
+                    items.push(parse_value(chars));
+                    if chars.peek() == Some(&',') {
+                        chars.next();
+                    }
+                }
+                chars.next();
+                Json::Array(items)
+            }
+            Some('{') => {
+                chars.next();
+                let mut fields = vec![];
+                while chars.peek() != Some(&'}') {
+                    let key = parse_string(chars);
+                    assert_eq!(chars.next(), Some(':'));
+                    fields.push((key, parse_value(chars)));
+                    if chars.peek() == Some(&',') {
+                        chars.next();
+                    }
+                }
+                chars.next();
+                Json::Object(fields)
+            }
+            _ => {
+                let mut number = String::new();
+                while let Some(c) = chars.next_if(|c| *c == '-' || c.is_ascii_digit()) {
+                    number.push(c);
+                }
+                Json::Number(number.parse().expect("a JSON value"))
+            }
+        }
+    }
+
+    /// Parses the quoted string starting at the next character, undoing the escapes
+    fn parse_string(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+        assert_eq!(chars.next(), Some('"'));
+        let mut string = String::new();
+
+        loop {
+            match chars.next().expect("an unterminated string") {
+                '"' => return string,
+                '\\' => match chars.next() {
+                    Some('n') => string.push('\n'),
+                    Some('u') => {
+                        let code = chars.take(4).collect::<String>();
+                        string
+                            .push(char::from_u32(u32::from_str_radix(&code, 16).unwrap()).unwrap());
+                    }
+                    Some(c) => string.push(c),
+                    None => panic!("an unterminated escape"),
+                },
+                c => {
+                    assert!(c >= ' ', "a raw control character in a string");
+                    string.push(c);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_json_export_holds_every_room_and_the_player() {
+        let (_, mut dungeon) = start();
+        let mut room = Room::new();
+        room.name = Some("The \"deep\" \\ end\n\t".to_string());
+        room.objects.add(Object::Gold, 3);
+        dungeon.rooms.insert(Location(-2, 0, 7), room);
+
+        let json = parse_json(&dungeon.to_json(Location(-2, 0, 7)));
+
+        let player = json.get("player").unwrap();
+        assert_eq!(player.get("x"), Some(&Json::Number(-2)));
+        assert_eq!(player.get("y"), Some(&Json::Number(0)));
+        assert_eq!(player.get("z"), Some(&Json::Number(7)));
+
+        let rooms = match json.get("rooms") {
+            Some(Json::Array(rooms)) => rooms,
+            rooms => panic!("rooms should be an array, not {:?}", rooms),
+        };
+        assert_eq!(rooms.len(), dungeon.rooms.len());
+
+        let room = rooms
+            .iter()
+            .find(|room| room.get("z") == Some(&Json::Number(7)))
+            .unwrap();
+        assert_eq!(
+            room.get("name"),
+            Some(&Json::String("The \"deep\" \\ end\n\t".to_string()))
+        );
+        assert_eq!(room.get("description"), Some(&Json::Null));
+        assert_eq!(
+            room.get("objects"),
+            Some(&Json::Array(vec![Json::Object(vec![
+                ("object".to_string(), Json::String("gold".to_string())),
+                ("count".to_string(), Json::Number(3)),
+            ])]))
+        );
+    }
+
+    /// The locations of every room in the dungeon
+    fn locations(dungeon: &Dungeon) -> HashSet<Location> {
+        dungeon.rooms.keys().copied().collect()
+    }
+
+    #[test]
+    fn filling_an_empty_room_removes_only_that_room() {
+        let (player, mut dungeon) = start();
+        dungeon.rooms.insert(Location(1, 0, 0), Room::new());
+        dungeon.rooms.insert(Location(-1, 0, 0), Room::new());
+        let mut expected = locations(&dungeon);
+        expected.remove(&Location(1, 0, 0));
+
+        assert!(fill(&player, &mut dungeon, &["east"]).is_ok());
+        assert_eq!(locations(&dungeon), expected);
+    }
+
+    #[test]
+    fn filling_a_room_with_objects_in_it_is_refused() {
+        let (player, mut dungeon) = start();
+        dungeon.rooms.insert(
+            Location(1, 0, 0),
+            Room::new().with_objects(vec![Object::Sledge]),
+        );
+        let before = locations(&dungeon);
+
+        assert!(matches!(
+            fill(&player, &mut dungeon, &["east"]),
+            Err(GameError::RoomNotEmpty)
+        ));
+        assert_eq!(locations(&dungeon), before);
+    }
+
+    /// A random number generator handing out a fixed script of rolls, each read by `gen::<f32>()`
+    /// as the given number. Running out of rolls fails the test
+    struct ScriptedRng(VecDeque<f32>);
+
+    impl RngCore for ScriptedRng {
+        fn next_u32(&mut self) -> u32 {
+            let roll = self.0.pop_front().expect("the script ran out of rolls");
+            ((roll * (1 << 24) as f32) as u32) << 8
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            u64::from(self.next_u32())
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for byte in dest {
+                *byte = self.next_u32() as u8;
+            }
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn a_room_rolling_only_a_sledge_holds_only_a_sledge() {
+        let (mut player, mut dungeon) = start();
+        player.inventory.add(Object::Sledge, 1);
+        player.equipped = Some(Object::Sledge);
+        let mut rng = ScriptedRng(VecDeque::from(vec![
+            0.5, // No collapse
+            0.0, // A sledge
+            0.5, // No ladder
+            0.5, // No gold
+            0.5, // No rope
+            0.5, // No key
+            0.5, // No food
+            0.5, // No trap
+            0.5, // Nothing hidden
+            0.0, // The first flavor description
+            0.5, // No gold vein
+        ]));
+
+        assert!(dig(
+            &mut player,
+            &mut dungeon,
+            &mut rng,
+            &GenerationConfig::default(),
+            &["east"]
+        )
+        .is_ok());
+        assert!(rng.0.is_empty());
+
+        let room = &dungeon.rooms[&Location(1, 0, 0)];
+        assert_eq!(
+            room.objects.iter().collect::<Vec<_>>(),
+            vec![(Object::Sledge, 1)]
+        );
+        assert!(room.hidden.is_empty());
+        assert!(!room.hazard);
+    }
+
+    /// Every command, a move for each direction included
+    fn every_command() -> Vec<Command> {
+        use Command::*;
+
+        let mut commands = DIRECTION_MAPPING
+            .iter()
+            .map(|(_, direction)| Move(*direction))
+            .collect::<Vec<Command>>();
+        commands.extend(&[
+            Help, Dig, Look, Inventory, Take, Drop, Equip, Unequip, Alias, Map, Map3D, Where, Name,
+            Seal, Teleport, Undo, Climb, Status, Dot, Json, Fill, Items, Search, Hint, Solve, Back,
+            Brief, Verbose, Normal, Stats, Repair, Buy, Sell, Count, Rest, Eat, Combine, Validate,
+            Record, End, Play, Restart, Go, Whoami, Rename, Give,
+        ]);
+
+        // Stops compiling when a command is added, until it is listed above as well
+        for command in &commands {
+            match command {
+                Move(_) | Help | Dig | Look | Inventory | Take | Drop | Equip | Unequip | Alias
+                | Map | Map3D | Where | Name | Seal | Teleport | Undo | Climb | Status | Dot
+                | Json | Fill | Items | Search | Hint | Solve | Back | Brief | Verbose | Normal
+                | Stats | Repair | Buy | Sell | Count | Rest | Eat | Combine | Validate
+                | Record | End | Play | Restart | Go | Whoami | Rename | Give => {}
+            }
+        }
+
+        commands
+    }
+
+    #[test]
+    fn every_command_can_be_issued_by_its_name() {
+        let aliases = default_aliases();
+
+        for command in every_command() {
+            assert_eq!(
+                find_command(command.name(), &aliases),
+                Some(command),
+                "{:?} is not issued by \"{}\"",
+                command,
+                command.name()
+            );
+        }
+    }
+
+    #[test]
+    fn every_move_walks_in_its_own_direction() {
+        let aliases = default_aliases();
+        let mut game = Game::new(&Options::default());
+        game.player.inventory.add(Object::Ladder, 1);
+
+        for (offset, direction) in DIRECTION_MAPPING.iter() {
+            let command = find_command(direction.name(), &aliases).unwrap();
+            game.player.location = Location(0, 0, 0);
+            game.dungeon.rooms.insert(*offset, Room::new());
+
+            assert!(command.handler()(&mut game, command, &[], &[]).is_ok());
+            assert_eq!(game.player.location, *offset);
+        }
+    }
+
+    #[test]
+    fn going_north_needs_no_ladder() {
+        let (mut player, mut dungeon) = start();
+        dungeon.rooms.get_mut(&Location(0, 0, 0)).unwrap().objects = Inventory::default();
+        dungeon.rooms.insert(Location(0, -1, 0), Room::new());
+
+        assert!(goto(
+            &mut player,
+            &mut dungeon,
+            Direction::North,
+            Verbosity::Brief
+        )
+        .is_ok());
+        assert_eq!(player.location, Location(0, -1, 0));
+    }
+
+    #[test]
+    fn going_up_needs_a_ladder_carried_or_on_the_floor() {
+        let (mut player, mut dungeon) = start();
+        dungeon.rooms.insert(Location(0, 0, -1), Room::new());
+        dungeon.rooms.insert(Location(0, 0, -2), Room::new());
+
+        // The first room has a ladder on the floor
+        assert!(goto(&mut player, &mut dungeon, Direction::Up, Verbosity::Brief).is_ok());
+        assert!(matches!(
+            goto(&mut player, &mut dungeon, Direction::Up, Verbosity::Brief),
+            Err(GameError::NeedLadder)
+        ));
+
+        player.inventory.add(Object::Ladder, 1);
+        assert!(goto(&mut player, &mut dungeon, Direction::Up, Verbosity::Brief).is_ok());
+        assert_eq!(player.inventory.count(Object::Ladder), 1);
+    }
+
+    #[test]
+    fn going_down_into_a_pit_needs_a_rope() {
+        let (mut player, mut dungeon) = start();
+        dungeon
+            .rooms
+            .insert(Location(0, 0, 1), Room::new().with_pit(true));
+
+        assert!(matches!(
+            goto(&mut player, &mut dungeon, Direction::Down, Verbosity::Brief),
+            Err(GameError::NeedRope)
+        ));
+        assert_eq!(player.location, Location(0, 0, 0));
+
+        player.inventory.add(Object::Rope, 1);
+        assert!(goto(&mut player, &mut dungeon, Direction::Down, Verbosity::Brief).is_ok());
+        assert_eq!(player.location, Location(0, 0, 1));
+        assert_eq!(player.inventory.count(Object::Rope), 1);
+    }
+
+    #[test]
+    fn the_map_tells_visited_rooms_from_unexplored_ones() {
+        let (mut player, mut dungeon) = start();
+        dungeon.rooms.insert(Location(1, 0, 0), Room::new());
+        dungeon.rooms.insert(Location(2, 0, 0), Room::new());
+
+        assert!(goto(&mut player, &mut dungeon, Direction::East, Verbosity::Brief).is_ok());
+
+        assert_eq!(
+            player.visited,
+            HashSet::from_iter(vec![Location(0, 0, 0), Location(1, 0, 0)])
+        );
+        assert_eq!(map(&player, &dungeon).unwrap(), "Level 0:\n#@?");
+    }
+
+    #[test]
+    fn a_vertical_shaft_lines_up_across_levels() {
+        let (mut player, _) = start();
+        let mut dungeon = Dungeon::new(&[]);
+        dungeon.rooms.insert(Location(1, 0, 0), Room::new());
+        dungeon.rooms.insert(Location(0, 0, 1), Room::new());
+        player.location = Location(1, 0, 0);
+
+        assert_eq!(
+            map_3d(&player, &dungeon).unwrap(),
+            "Level 0:\n|@\nLevel 1:\n|."
+        );
+        assert_eq!(map(&player, &dungeon).unwrap(), "Level 0:\n#@");
+    }
+
+    #[test]
+    fn rooms_can_be_named_with_accents_and_cjk_text() {
+        let (player, mut dungeon) = start();
+
+        assert_eq!(
+            tokenize("name \"Salle d'Été\""),
+            vec!["name".to_string(), "Salle d'Été".to_string()]
+        );
+        assert!(name(&player, &mut dungeon, &["地下の", "大広間"]).is_ok());
+
+        assert_eq!(
+            dungeon.rooms[&Location(0, 0, 0)].name.as_deref(),
+            Some("地下の 大広間")
+        );
+        assert!(describe_room(&player, &dungeon, Verbosity::Brief)
+            .unwrap()
+            .contains("地下の 大広間"));
+    }
+
+    #[test]
+    fn sealing_fills_only_the_empty_dead_ends_left_behind() {
+        let mut game = Game::new(&Options::default());
+        let mut empty = Room::new();
+        empty.dug = true;
+        let mut holding_a_rope = Room::new().with_objects(vec![Object::Rope]);
+        holding_a_rope.dug = true;
+        game.dungeon.rooms.insert(Location(1, 0, 0), empty);
+        game.dungeon.rooms.insert(Location(0, 1, 0), holding_a_rope);
+
+        game.step("seal on");
+        game.step("east");
+        game.step("west");
+        game.step("south");
+        game.step("north");
+
+        assert!(!game.dungeon.rooms.contains_key(&Location(1, 0, 0)));
+        assert!(game.dungeon.rooms.contains_key(&Location(0, 1, 0)));
+        assert!(game.dungeon.rooms.contains_key(&Location(0, 0, 0)));
+    }
+
+    #[test]
+    fn a_game_runs_over_any_input_and_output() {
+        let mut game = Game::new(&Options::default());
+        game.set_prompt("");
+        let mut output = vec![];
+
+        game.run(io::Cursor::new("take ladder\ninventory\n"), &mut output)
+            .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let taken = output.find("Taken").unwrap();
+        let carrying = output.find("You are carrying: a ladder").unwrap();
+        assert!(output.starts_with(&game.intro()));
+        assert!(taken < carrying);
+    }
+
+    #[test]
+    fn teleporting_takes_three_numbers_and_the_debug_flag() {
+        let (mut player, mut dungeon) = start();
+
+        assert!(matches!(
+            teleport(&mut player, &mut dungeon, false, &["4", "-2", "7"]),
+            Err(GameError::TeleportDisabled)
+        ));
+        for args in &[
+            &["4", "x", "7"][..],
+            &["4", "-2"],
+            &["4", "-2", "7", "1"],
+            &[],
+        ] {
+            assert!(matches!(
+                teleport(&mut player, &mut dungeon, true, args),
+                Err(GameError::Usage("usage.goto"))
+            ));
+        }
+        assert_eq!(player.location, Location(0, 0, 0));
+
+        assert!(teleport(&mut player, &mut dungeon, true, &["4", "-2", "7"]).is_ok());
+        assert_eq!(player.location, Location(4, -2, 7));
+        assert!(dungeon.rooms.contains_key(&Location(4, -2, 7)));
+    }
+
+    #[test]
+    fn undoing_a_dig_removes_the_room_and_puts_the_player_back() {
+        let mut game = Game::new(&Options::default());
+        game.step("take sledge");
+        game.step("equip sledge");
+        game.step("dig east");
+        game.step("east");
+        let history = game.history.len();
+        game.step("look");
+        assert_eq!(game.history.len(), history);
+
+        game.step("undo");
+        assert_eq!(game.player.location, Location(0, 0, 0));
+        assert!(game.dungeon.rooms.contains_key(&Location(1, 0, 0)));
+
+        game.step("undo");
+        assert!(!game.dungeon.rooms.contains_key(&Location(1, 0, 0)));
+        assert_eq!(game.player.equipped, Some(Object::Sledge));
+    }
+
+    #[test]
+    fn digging_twice_the_same_way_makes_a_single_room() {
+        let (mut player, mut dungeon) = start();
+        player.inventory.add(Object::Sledge, 1);
+        player.equipped = Some(Object::Sledge);
+        let mut rng = StdRng::seed_from_u64(1);
+        let config = GenerationConfig::default();
+
+        assert!(dig(&mut player, &mut dungeon, &mut rng, &config, &["east"]).is_ok());
+        let rooms = dungeon.rooms.len();
+        let objects = |dungeon: &Dungeon| {
+            dungeon.rooms[&Location(1, 0, 0)]
+                .objects
+                .iter()
+                .collect::<Vec<_>>()
+        };
+        let before = objects(&dungeon);
+
+        assert!(matches!(
+            dig(&mut player, &mut dungeon, &mut rng, &config, &["east"]),
+            Err(GameError::ExitAlreadyExists(Direction::East))
+        ));
+        assert_eq!(dungeon.rooms.len(), rooms);
+        assert_eq!(objects(&dungeon), before);
+    }
+
+    #[test]
+    fn every_command_has_help_of_its_own() {
+        let aliases = default_aliases();
+
+        for command in every_command() {
+            let text = command_help(command);
+            assert!(
+                text.contains(command.name()),
+                "the help of {:?} doesn't mention it: {}",
+                command,
+                text
+            );
+            assert_eq!(help(&aliases, &[command.name()]).unwrap(), text);
+        }
+        assert!(matches!(
+            help(&aliases, &["xyzzy"]),
+            Err(GameError::UnknownHelpTopic(_))
+        ));
+    }
+
+    #[test]
+    fn quoted_words_stay_together() {
+        assert_eq!(
+            tokenize("name \"Hall of Echoes\" now"),
+            vec!["name", "Hall of Echoes", "now"]
+        );
+        assert_eq!(tokenize("  dig   north  "), vec!["dig", "north"]);
+        assert_eq!(tokenize("name \"\""), vec!["name", ""]);
+        assert_eq!(
+            tokenize("name \"Unfinished business"),
+            vec!["name", "Unfinished business"]
+        );
+        assert!(tokenize("").is_empty());
+    }
+
+    #[test]
+    fn only_a_number_is_read_as_a_count() {
+        assert_eq!(
+            parse_quantity(&["3", "gold"], &CustomObjects::default(), TAKE_USAGE).unwrap(),
+            (Some(3), "gold")
+        );
+        assert_eq!(
+            parse_quantity(&["gold", "extra"], &CustomObjects::default(), TAKE_USAGE).unwrap(),
+            (None, "gold")
+        );
+        assert_eq!(
+            parse_quantity(&["all"], &CustomObjects::default(), TAKE_USAGE).unwrap(),
+            (None, "all")
+        );
+        for args in &[&["-2", "gold"][..], &["0", "gold"], &["abc", "gold"], &[]] {
+            assert!(matches!(
+                parse_quantity(args, &CustomObjects::default(), TAKE_USAGE),
+                Err(GameError::Usage(TAKE_USAGE))
+            ));
+        }
+    }
+
+    #[test]
+    fn taking_all_takes_every_object_in_the_room() {
+        let (mut player, mut dungeon) = start();
+
+        assert!(take(
+            &mut player,
+            &mut dungeon,
+            &["all"],
+            &CustomObjects::default()
+        )
+        .is_ok());
+        assert_eq!(player.inventory.count(Object::Sledge), 1);
+        assert_eq!(player.inventory.count(Object::Ladder), 1);
+        assert!(dungeon.rooms[&Location(0, 0, 0)].objects.is_empty());
+
+        let (result, player, _) = take_from_five_gold(&["gold", "please"]);
+        assert!(result.is_ok());
+        assert_eq!(player.inventory.count(Object::Gold), 5);
+    }
+
+    #[test]
+    fn dropping_a_count_adds_to_the_gold_on_the_floor() {
+        let (mut player, mut dungeon) = start();
+        player.inventory.add(Object::Gold, 5);
+        dungeon
+            .rooms
+            .get_mut(&Location(0, 0, 0))
+            .unwrap()
+            .objects
+            .add(Object::Gold, 1);
+
+        assert!(drop(
+            &mut player,
+            &mut dungeon,
+            &["2", "gold"],
+            &CustomObjects::default()
+        )
+        .is_ok());
+        assert_eq!(player.inventory.count(Object::Gold), 3);
+        assert_eq!(
+            dungeon.rooms[&Location(0, 0, 0)]
+                .objects
+                .count(Object::Gold),
+            3
+        );
+
+        assert_eq!(
+            drop(
+                &mut player,
+                &mut dungeon,
+                &["7", "gold"],
+                &CustomObjects::default()
+            )
+            .unwrap(),
+            "You only had 3 gold, you dropped all of it"
+        );
+        assert_eq!(player.inventory.count(Object::Gold), 0);
+        assert_eq!(
+            dungeon.rooms[&Location(0, 0, 0)]
+                .objects
+                .count(Object::Gold),
+            6
+        );
+    }
+
+    #[test]
+    fn a_hazard_hurts_only_the_first_time() {
+        let (mut player, mut dungeon) = start();
+        let mut spikes = Room::new();
+        spikes.hazard = true;
+        dungeon.rooms.insert(Location(1, 0, 0), spikes);
+        dungeon.rooms.insert(Location(-1, 0, 0), Room::new());
+
+        for _ in 0..2 {
+            assert!(goto(&mut player, &mut dungeon, Direction::East, Verbosity::Brief).is_ok());
+            assert!(goto(&mut player, &mut dungeon, Direction::West, Verbosity::Brief).is_ok());
+        }
+        assert_eq!(player.health, 10 - HAZARD_DAMAGE);
+
+        assert!(goto(&mut player, &mut dungeon, Direction::West, Verbosity::Brief).is_ok());
+        assert_eq!(player.health, 10 - HAZARD_DAMAGE);
+    }
+
+    #[test]
+    fn lethal_damage_ends_the_game() {
+        let mut game = Game::new(&Options::default());
+        let mut spikes = Room::new();
+        spikes.hazard = true;
+        game.dungeon.rooms.insert(Location(1, 0, 0), spikes);
+        game.player.health = HAZARD_DAMAGE - 1;
+
+        let output = game.step("east");
+
+        assert!(output.ends_with(&message("game.death")));
+        assert_eq!(game.player.health, 0);
+        assert!(game.over);
+        assert!(!game.won);
+    }
+
+    #[test]
+    fn health_never_goes_above_the_max() {
+        let (mut player, _) = start();
+        player.health = player.max_health - 1;
+
+        assert!(rest(&mut player, &mut ScriptedRng(VecDeque::from(vec![0.5]))).is_ok());
+        assert_eq!(player.health, player.max_health);
+        assert!(matches!(
+            rest(&mut player, &mut ScriptedRng(VecDeque::new())),
+            Err(GameError::WellRested)
+        ));
+    }
+
+    #[test]
+    fn two_rooms_make_a_graph_with_a_single_edge() {
+        let mut dungeon = Dungeon::new(&[]);
+        let mut hall = Room::new();
+        hall.name = Some("The \"Hall\"".to_string());
+        dungeon.rooms.insert(Location(1, 0, 0), hall);
+
+        let dot = dungeon.to_dot();
+        let edges = dot
+            .lines()
+            .filter(|line| line.contains(" -- "))
+            .collect::<Vec<_>>();
+
+        assert_eq!(edges, vec!["    \"0,0,0\" -- \"1,0,0\" [label=\"east\"];"]);
+        assert!(dot.contains("    \"1,0,0\" [label=\"The \\\"Hall\\\"\"];"));
+        assert_eq!(dot, dungeon.to_dot());
+    }
+
+    #[test]
+    fn the_example_map_loads_as_written() {
+        let dungeon = Dungeon::from_file(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/maps/example.toml"),
+            &CustomObjects::default(),
+        )
+        .unwrap();
+        let objects =
+            |location: Location| dungeon.rooms[&location].objects.iter().collect::<Vec<_>>();
+
+        assert_eq!(
+            locations(&dungeon),
+            HashSet::from_iter(vec![
+                Location(0, 0, 0),
+                Location(1, 0, 0),
+                Location(1, 0, 1),
+                Location(1, 1, 1),
+            ])
+        );
+        assert_eq!(dungeon.goals, vec![Location(1, 1, 1)]);
+        assert_eq!(
+            dungeon.rooms[&Location(0, 0, 0)].name.as_deref(),
+            Some("Entrance")
+        );
+        assert!(dungeon.rooms[&Location(1, 0, 0)].forge);
+        assert_eq!(
+            objects(Location(1, 0, 0)),
+            vec![(Object::Rope, 1), (Object::Key, 1)]
+        );
+        assert_eq!(objects(Location(1, 0, 1)), vec![(Object::Gold, 3)]);
+        assert!(dungeon.rooms[&Location(1, 0, 0)]
+            .locked
+            .contains(&Direction::Down));
+    }
+
+    #[test]
+    fn objects_always_or_never_spawn_at_probabilities_one_and_zero() {
+        let (mut player, mut dungeon) = start();
+        player.inventory.add(Object::Sledge, 1);
+        player.equipped = Some(Object::Sledge);
+        let config = GenerationConfig {
+            spawn_probabilities: vec![(Object::Rope, 1.0), (Object::Gold, 0.0)],
+            vein_probability: 0.0,
+            ..GenerationConfig::default()
+        };
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for x in 1..=20 {
+            assert!(dig(&mut player, &mut dungeon, &mut rng, &config, &["east"]).is_ok());
+            player.location = Location(x, 0, 0);
+
+            let objects = &dungeon.rooms[&player.location].objects;
+            assert_eq!(objects.count(Object::Rope), 1);
+            assert_eq!(objects.count(Object::Gold), 0);
+        }
+    }
+
+    #[test]
+    fn digging_past_the_most_rooms_there_can_be_is_refused() {
+        let (mut player, mut dungeon) = start();
+        player.inventory.add(Object::Sledge, 1);
+        player.equipped = Some(Object::Sledge);
+        let config = GenerationConfig {
+            max_rooms: Some(4),
+            ..GenerationConfig::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+
+        // The first room and the prize room are there from the start
+        assert!(dig(&mut player, &mut dungeon, &mut rng, &config, &["east"]).is_ok());
+        assert!(dig(&mut player, &mut dungeon, &mut rng, &config, &["west"]).is_ok());
+        assert!(matches!(
+            dig(&mut player, &mut dungeon, &mut rng, &config, &["north"]),
+            Err(GameError::RockTooHard)
+        ));
+        assert_eq!(dungeon.rooms.len(), 4);
+        assert!(!dungeon.rooms.contains_key(&Location(0, -1, 0)));
+    }
+
+    #[test]
+    fn tunnels_always_or_never_collapse_at_probabilities_one_and_zero() {
+        let mut rng = StdRng::seed_from_u64(3);
+
+        for (probability, holds) in &[(1.0, false), (0.0, true)] {
+            let (mut player, mut dungeon) = start();
+            player.inventory.add(Object::Sledge, 1);
+            player.equipped = Some(Object::Sledge);
+            let config = GenerationConfig {
+                collapse_probability: *probability,
+                ..GenerationConfig::default()
+            };
+
+            for direction in &["north", "south", "west", "east"] {
+                assert!(dig(&mut player, &mut dungeon, &mut rng, &config, &[direction]).is_ok());
+            }
+
+            assert_eq!(dungeon.rooms.len(), if *holds { 6 } else { 2 });
+            assert_eq!(
+                player.health,
+                if *holds { 10 } else { 10 - 4 * COLLAPSE_DAMAGE }
+            );
+        }
+    }
+
+    #[test]
+    fn digging_several_ways_reports_each_one_in_order() {
+        let (_, mut dungeon) = start();
+        let mut player = Player::new(10, Inventory::default(), Some(2));
+        player.inventory.add(Object::Sledge, 1);
+        player.equipped = Some(Object::Sledge);
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let report = dig(
+            &mut player,
+            &mut dungeon,
+            &mut rng,
+            &GenerationConfig::default(),
+            &["east", "xyzzy", "east", "south", "north"],
+        )
+        .unwrap();
+        assert_eq!(
+            report,
+            [
+                "east: There is now an exit eastward",
+                "xyzzy: That is not a direction I recognize",
+                "east: There is already an exit, there!",
+                "south: There is now an exit southward",
+                "Your sledge breaks under the strain!",
+                "north: With your bare hands?",
+            ]
+            .join("\n")
+        );
+        assert!(dungeon.rooms.contains_key(&Location(1, 0, 0)));
+        assert!(dungeon.rooms.contains_key(&Location(0, 1, 0)));
+        assert!(!dungeon.rooms.contains_key(&Location(0, -1, 0)));
+        assert_eq!(player.equipped, None);
+    }
+
+    #[test]
+    fn a_seeded_dig_always_gets_the_same_description() {
+        let dig_east = |seed| {
+            let (mut player, mut dungeon) = start();
+            player.inventory.add(Object::Sledge, 1);
+            player.equipped = Some(Object::Sledge);
+            let mut rng = StdRng::seed_from_u64(seed);
+            dig(
+                &mut player,
+                &mut dungeon,
+                &mut rng,
+                &GenerationConfig::default(),
+                &["east"],
+            )
+            .unwrap();
+
+            dungeon.rooms[&Location(1, 0, 0)].description.clone()
+        };
+        let pool = Biome::at(Location(1, 0, 0))
+            .descriptions()
+            .iter()
+            .map(|id| message(id))
+            .collect::<Vec<_>>();
+
+        for seed in 0..10 {
+            let description = dig_east(seed).unwrap();
+            assert!(pool.contains(&description));
+            assert_eq!(dig_east(seed), Some(description));
+        }
+    }
+
+    #[test]
+    fn the_log_holds_every_command_in_order() {
+        let path = std::env::temp_dir().join(format!("rcrpg-log-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        let mut game = Game::new(&Options::default());
+        game.open_log(path).unwrap();
+
+        game.run(
+            io::Cursor::new("look\ntake ladder\ninventory\n"),
+            &mut vec![],
+        )
+        .unwrap();
+
+        let log = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let inputs = log
+            .lines()
+            .filter_map(|line| line.split_once(" > ").map(|(_, input)| input))
+            .collect::<Vec<_>>();
+        assert_eq!(inputs, vec!["look", "take ladder", "inventory"]);
+        assert!(log.lines().all(|line| line.starts_with('[')));
+        assert!(log.contains("You are carrying: a ladder"));
+    }
+
+    #[test]
+    fn edit_distances_count_single_character_changes() {
+        assert_eq!(levenshtein("drp", "drop"), 1);
+        assert_eq!(levenshtein("drop", "drop"), 0);
+        assert_eq!(levenshtein("", "dig"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("été", "ete"), 2);
+    }
+
+    #[test]
+    fn mistyped_commands_get_the_closest_alias_suggested() {
+        let aliases = default_aliases();
+
+        assert_eq!(closest_alias("drp", &aliases).as_deref(), Some("drop"));
+        assert_eq!(
+            closest_alias("INVENTRY", &aliases).as_deref(),
+            Some("inventory")
+        );
+        // One edit away from `n`, `s`, `w`... but nothing like them
+        assert_eq!(closest_alias("x", &aliases), None);
+        assert_eq!(closest_alias("xyzzyplugh", &aliases), None);
+        assert_eq!(
+            Game::new(&Options::default()).step("drp"),
+            "Did you mean 'drop'?"
+        );
+    }
+
+    #[test]
+    fn object_counts_read_as_english() {
+        assert_eq!(Object::Ladder.display_count(1), "a ladder");
+        assert_eq!(Object::Ladder.display_count(2), "2 ladders");
+        assert_eq!(Object::Gold.display_count(1), "some gold");
+        assert_eq!(Object::Gold.display_count(3), "3 gold");
+
+        let mut player = Player::new(10, Inventory::default(), None);
+        player.inventory.add(Object::Rope, 2);
+        player.inventory.add(Object::Gold, 1);
+        assert_eq!(
+            inventory(&player).unwrap(),
+            "You are carrying: 2 ropes, some gold"
+        );
+    }
+
+    #[test]
+    fn a_locked_passage_takes_a_key_once() {
+        let (mut player, mut dungeon) = start();
+        dungeon.rooms.insert(Location(1, 0, 0), Room::new());
+        assert!(dungeon.lock(Location(0, 0, 0), Direction::East));
+        assert!(describe_room(&player, &dungeon, Verbosity::Verbose)
+            .unwrap()
+            .contains("There is a locked passage eastward."));
+
+        assert!(matches!(
+            goto(&mut player, &mut dungeon, Direction::East, Verbosity::Brief),
+            Err(GameError::Locked)
+        ));
+        assert_eq!(player.location, Location(0, 0, 0));
+
+        player.inventory.add(Object::Key, 1);
+        assert!(
+            goto(&mut player, &mut dungeon, Direction::East, Verbosity::Brief)
+                .unwrap()
+                .starts_with("You unlock the passage with your key.")
+        );
+        assert!(!player.inventory.contains(Object::Key));
+
+        // Unlocked from both sides for good
+        assert!(goto(&mut player, &mut dungeon, Direction::West, Verbosity::Brief).is_ok());
+        assert!(goto(&mut player, &mut dungeon, Direction::East, Verbosity::Brief).is_ok());
+        assert_eq!(player.location, Location(1, 0, 0));
+    }
+
+    #[test]
+    fn the_game_ends_when_the_input_runs_out() {
+        let mut game = Game::new(&Options::default());
+        let mut output = vec![];
+
+        game.run(io::Cursor::new("look\ninventory"), &mut output)
+            .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.ends_with(&format!("> \n{}\n", message("game.goodbye"))));
+        assert_eq!(output.matches("> ").count(), 3);
+    }
+
+    #[test]
+    fn digging_after_dropping_the_sledge_in_hand_is_done_bare_handed() {
+        let (mut player, mut dungeon) = start();
+        player.inventory.add(Object::Sledge, 1);
+        player.equipped = Some(Object::Sledge);
+
+        assert!(drop(
+            &mut player,
+            &mut dungeon,
+            &["sledge"],
+            &CustomObjects::default()
+        )
+        .unwrap()
+        .ends_with("Your hands are empty now"));
+        assert_eq!(player.equipped, None);
+
+        let error = dig(
+            &mut player,
+            &mut dungeon,
+            &mut StdRng::seed_from_u64(1),
+            &GenerationConfig::default(),
+            &["east"],
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "With your bare hands?");
+        assert!(!dungeon.rooms.contains_key(&Location(1, 0, 0)));
+    }
+
+    #[test]
+    fn the_shortest_path_goes_around_what_the_player_cannot_climb() {
+        let (mut player, _) = start();
+        let mut dungeon = Dungeon::new(&[]);
+        // A dead end north, a corridor east and, at its end, a room above and the way down
+        for location in &[
+            Location(0, -1, 0),
+            Location(1, 0, 0),
+            Location(2, 0, 0),
+            Location(2, 0, -1),
+            Location(2, 0, 1),
+            Location(2, 1, 1),
+        ] {
+            dungeon.rooms.insert(*location, Room::new());
+        }
+
+        assert_eq!(
+            dungeon.path_to(Location(0, 0, 0), Location(2, 1, 1), &player),
+            Some(vec![
+                Direction::East,
+                Direction::East,
+                Direction::Down,
+                Direction::South
+            ])
+        );
+        assert_eq!(
+            dungeon.path_to(Location(0, 0, 0), Location(0, 0, 0), &player),
+            Some(vec![])
+        );
+        assert_eq!(
+            dungeon.path_to(Location(0, 0, 0), Location(2, 0, -1), &player),
+            None
+        );
+
+        player.inventory.add(Object::Ladder, 1);
+        assert_eq!(
+            dungeon.path_to(Location(0, 0, 0), Location(2, 0, -1), &player),
+            Some(vec![Direction::East, Direction::East, Direction::Up])
+        );
+    }
+
+    #[test]
+    fn aliases_of_other_commands_cannot_be_taken() {
+        let mut aliases = default_aliases();
+
+        assert!(matches!(
+            alias(&mut aliases, &["dig", "n"]),
+            Err(GameError::AliasTaken(alias, Command::Move(Direction::North))) if alias == "n"
+        ));
+        assert!(matches!(
+            alias(&mut aliases, &["dig", "north"]),
+            Err(GameError::ReservedWord(Command::Move(Direction::North)))
+        ));
+        assert_eq!(
+            alias(&mut aliases, &["dig", "excavate"]).unwrap(),
+            "You can use \"excavate\" in lieu of \"dig\""
+        );
+        assert!(alias(&mut aliases, &["dig", "excavate"]).is_ok());
+        assert!(matches!(
+            alias(&mut aliases, &["take", "excavate"]),
+            Err(GameError::AliasTaken(_, Command::Dig))
+        ));
+
+        assert_eq!(
+            find_command("n", &aliases),
+            Some(Command::Move(Direction::North))
+        );
+        assert_eq!(find_command("excavate", &aliases), Some(Command::Dig));
+    }
+
+    #[test]
+    fn a_custom_object_can_be_taken_dropped_and_saved() {
+        let mut options = Options::default();
+        options.generation.objects =
+            CustomObjects::load("[[object]]\nname = \"pickaxe\"\ndigs = true").unwrap();
+        let pickaxe = Object::Custom(options.generation.objects.find("pickaxe").unwrap());
+        let mut game = Game::new(&options);
+        let location = game.player.location;
+        game.dungeon
+            .rooms
+            .get_mut(&location)
+            .unwrap()
+            .objects
+            .add(pickaxe, 1);
+
+        game.step("take pickaxe");
+        assert!(game.player.inventory.contains(pickaxe));
+        game.step("drop pickaxe");
+        assert!(game.dungeon.rooms[&location].objects.contains(pickaxe));
+        game.step("take pickaxe");
+
+        let loaded = save::read(&save::write(&game), &options).unwrap();
+        assert!(loaded.player.inventory.contains(pickaxe));
+        assert!(save::read(&save::write(&game), &Options::default()).is_err());
+    }
+
+    #[test]
+    fn the_prize_of_a_generated_dungeon_can_always_be_walked_to() {
+        let start = Location(0, 0, 0);
+
+        for seed in 0..200 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let dungeon = Dungeon::generate(&mut rng, &GenerationConfig::default(), 30);
+            let explorer = Player::new(1, dungeon.rooms[&start].objects.clone(), None);
+
+            for goal in &dungeon.goals {
+                assert!(
+                    dungeon.path_to(start, *goal, &explorer).is_some(),
+                    "seed {}",
+                    seed
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn generated_dungeons_are_connected_whatever_their_size() {
+        for seed in 0..100 {
+            for size in [1, 2, 10, 50] {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let dungeon = Dungeon::generate(&mut rng, &GenerationConfig::default(), size);
+
+                assert!(dungeon.is_connected(), "seed {}, size {}", seed, size);
+                assert_eq!(dungeon.rooms.len(), size.max(2));
+            }
+        }
+    }
+
+    #[test]
+    fn a_step_of_inventory_lists_what_the_player_carries() {
+        let mut game = Game::new(&Options::default());
+        assert_eq!(game.step("inventory"), "You are carrying: a sledge");
+
+        game.step("take all");
+        let inventory = game.step("INVENTORY");
+        assert!(inventory.starts_with("You are carrying: "), "{}", inventory);
+        assert!(inventory.contains("ladder") && inventory.contains("sledge"));
+        assert_eq!(game.step("i"), inventory);
+
+        game.step("drop all");
+        assert_eq!(game.step("inventory"), message("inventory.empty"));
+    }
+
+    #[test]
+    fn durations_are_shown_as_minutes_and_seconds() {
+        assert_eq!(format_duration(Duration::from_secs(5)), "00:05");
+        assert_eq!(format_duration(Duration::from_secs(125)), "02:05");
+        assert_eq!(format_duration(Duration::from_millis(59_999)), "00:59");
+        assert_eq!(format_duration(Duration::from_secs(6000)), "100:00");
+    }
+
+    #[test]
+    fn games_are_autosaved_to_the_file_of_the_options() {
+        let path = std::env::temp_dir().join(format!("rcrpg-autosave-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        let mut options = Options {
+            autosave: 1,
+            ..Options::default()
+        };
+        options.set_autosave_path(path);
+        let mut game = Game::new(&options);
+
+        game.step("equip sledge");
+        game.step("dig east");
+        game.step("east");
+
+        let saved = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(save::read(&saved, &options).is_ok());
+    }
+
+    #[test]
+    fn nothing_goes_past_the_edge_of_the_world() {
+        let edge = Location(i32::MAX, 0, 0);
+        assert_eq!(edge.checked_add(Direction::East.to_location()), None);
+        assert_eq!(
+            edge.checked_add(Direction::West.to_location()),
+            Some(Location(i32::MAX - 1, 0, 0))
+        );
+
+        let (mut player, mut dungeon) = start();
+        dungeon.rooms.insert(edge, Room::new());
+        player.location = edge;
+        player.inventory.add(Object::Sledge, 1);
+        player.equipped = Some(Object::Sledge);
+        let rooms = dungeon.rooms.len();
+        let mut rng = StdRng::seed_from_u64(1);
+
+        assert!(matches!(
+            dig_towards(
+                &mut player,
+                &mut dungeon,
+                &mut rng,
+                &GenerationConfig::default(),
+                "east"
+            ),
+            Err(GameError::EdgeOfWorld)
+        ));
+        assert!(matches!(
+            goto(&mut player, &mut dungeon, Direction::East, Verbosity::Brief),
+            Err(GameError::EdgeOfWorld)
+        ));
+        assert_eq!(dungeon.rooms.len(), rooms);
+        assert_eq!(player.location, edge);
+    }
+
+    #[test]
+    fn looking_around_where_there_is_no_room_is_an_error() {
+        let (mut player, dungeon) = start();
+        player.location = Location(5, 5, 5);
+
+        assert!(matches!(look(&player, &dungeon), Err(GameError::NoRoom)));
+        assert!(matches!(items(&player, &dungeon), Err(GameError::NoRoom)));
+
+        let mut game = Game::new(&Options::default());
+        game.player.location = Location(5, 5, 5);
+        assert_eq!(game.step("look"), GameError::NoRoom.to_string());
+    }
+}
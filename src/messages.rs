@@ -0,0 +1,263 @@
+//! The text shown to the player, looked up by message id so that it can be translated or
+//! reskinned. The English messages are read from `messages.toml`, built into the game. Another
+//! language can be loaded from a file with `--lang`, and templates overriding some of the messages,
+//! like room descriptions, with `--templates`. Both kinds of files use the same TOML subset as
+//! layouts, one message per line, keyed by its id:
+//!
+//! ```toml
+//! error.no-exit = "Il n'y a pas de sortie dans cette direction !"
+//! room.at = "Salle en {location}."
+//! ```
+//!
+//! Messages can hold placeholders like `{location}`, replaced by the values given along with the
+//! id. Templates take precedence over the language. Messages missing from the language file are
+//! shown in English, with a warning the first time, while templates are expected to only change a
+//! few of them.
+//!
+//! The files loaded are kept in the options of the game as `Messages`. The game makes them the
+//! ones `message` and `message_with` look up on its thread while it runs (see `Messages::speak`),
+//! so that games in different languages can be played side by side.
+
+use crate::layout::{parse_value, Value};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Display;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// The text of the built-in messages, in English
+const ENGLISH: &str = include_str!("messages.toml");
+
+/// The built-in messages, read from `ENGLISH` the first time they are needed
+static DEFAULTS: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+thread_local! {
+    /// The messages looked up on this thread, if others than the English ones
+    static SPOKEN: RefCell<Option<Messages>> = const { RefCell::new(None) };
+}
+
+/// The messages of a game: the English ones, unless a language or templates were loaded
+#[derive(Clone, Default)]
+pub struct Messages {
+    /// The messages of the language loaded with `--lang`, if any
+    language: Option<Arc<HashMap<String, String>>>,
+    /// The messages of the templates loaded with `--templates`, if any
+    templates: Option<Arc<HashMap<String, String>>>,
+    /// Ids of the messages already reported missing from the language, to warn only once about
+    /// each
+    missing: Arc<Mutex<BTreeSet<String>>>,
+}
+
+impl Messages {
+    /// Shows the messages read from a language file instead of the English ones
+    pub fn load_language(&mut self, path: &str) -> Result<(), String> {
+        self.language = Some(Arc::new(read(path)?));
+        Ok(())
+    }
+
+    /// Shows the messages read from a templates file instead of the usual ones
+    pub fn load_templates(&mut self, path: &str) -> Result<(), String> {
+        self.templates = Some(Arc::new(read(path)?));
+        Ok(())
+    }
+
+    /// Makes these the messages looked up on this thread until the returned guard is dropped
+    pub fn speak(&self) -> Speaking {
+        Speaking {
+            previous: SPOKEN.with(|spoken| spoken.replace(Some(self.clone()))),
+        }
+    }
+
+    /// The text of a message, its placeholders replaced by the given values
+    fn text(&self, id: &str, values: &[(&str, &dyn Display)]) -> String {
+        let template = self
+            .templates
+            .as_ref()
+            .and_then(|templates| templates.get(id));
+        let text = match (template, &self.language) {
+            (Some(template), _) => template,
+            (None, None) => english(id),
+            (None, Some(language)) => match language.get(id) {
+                Some(text) => text,
+                None => {
+                    if self.missing.lock().unwrap().insert(id.to_string()) {
+                        eprintln!(
+                            "{}",
+                            substitute(english("messages.untranslated"), &[("id", &id)])
+                        );
+                    }
+                    english(id)
+                }
+            },
+        };
+
+        substitute(text, values)
+    }
+}
+
+/// Keeps messages looked up on a thread (see `Messages::speak`), putting back the ones looked up
+/// before when dropped
+#[must_use]
+pub struct Speaking {
+    previous: Option<Messages>,
+}
+
+impl Drop for Speaking {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        SPOKEN.with(|spoken| *spoken.borrow_mut() = previous);
+    }
+}
+
+/// The built-in messages, by id
+fn defaults() -> &'static HashMap<String, String> {
+    DEFAULTS.get_or_init(|| {
+        parse(ENGLISH, None).unwrap_or_else(|error| panic!("messages.toml: {}", error))
+    })
+}
+
+/// The English text of a message, or its id if there is no such message
+fn english(id: &str) -> &str {
+    defaults().get(id).map(String::as_str).unwrap_or(id)
+}
+
+/// Reads the messages in the file at `path`, which can only be ones the game knows of
+fn read(path: &str) -> Result<HashMap<String, String>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|error| format!("Cannot read the messages in {}: {}", path, error))?;
+
+    parse(&text, Some(defaults())).map_err(|error| format!("{}: {}", path, error))
+}
+
+/// Reads the messages out of the text of a messages file, refusing the ones missing from `known`
+/// if given. Errors tell the line they were found on
+fn parse(
+    text: &str,
+    known: Option<&HashMap<String, String>>,
+) -> Result<HashMap<String, String>, String> {
+    let mut messages = HashMap::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let error = |message: &str| format!("line {}: {}", index + 1, message);
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (id, value) = line
+            .split_once('=')
+            .ok_or_else(|| error("expected ID = \"TEXT\""))?;
+        let id = id.trim();
+        if known.is_some_and(|known| !known.contains_key(id)) {
+            return Err(error(&format!("unknown message \"{}\"", id)));
+        }
+
+        match parse_value(value.trim()).map_err(|e| error(&e))? {
+            (Value::Text(text), rest) if rest.trim().is_empty() => {
+                messages.insert(id.to_string(), text);
+            }
+            _ => return Err(error("a message is a single text")),
+        }
+    }
+
+    Ok(messages)
+}
+
+/// The text of a message in the language being used
+pub fn message(id: &str) -> String {
+    message_with(id, &[])
+}
+
+/// The text of a message in the language being used, its placeholders replaced by the given
+/// values
+pub fn message_with(id: &str, values: &[(&str, &dyn Display)]) -> String {
+    SPOKEN.with(|spoken| match &*spoken.borrow() {
+        Some(messages) => messages.text(id, values),
+        None => substitute(english(id), values),
+    })
+}
+
+/// Replaces the placeholders of `text` by the given values in a single pass, so that braces in the
+/// values themselves are left alone. Placeholders without a value are kept as they are
+fn substitute(text: &str, values: &[(&str, &dyn Display)]) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let value = rest.find('}').and_then(|end| {
+            values
+                .iter()
+                .find(|(placeholder, _)| *placeholder == &rest[1..end])
+                .map(|(_, value)| (end, value))
+        });
+        match value {
+            Some((end, value)) => {
+                result.push_str(&value.to_string());
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_are_replaced_by_their_values() {
+        assert_eq!(
+            substitute(
+                "Moved {count} {object} {direction}ward.",
+                &[("count", &3), ("object", &"rocks"), ("direction", &"east")]
+            ),
+            "Moved 3 rocks eastward."
+        );
+    }
+
+    #[test]
+    fn braces_in_values_are_left_alone() {
+        assert_eq!(
+            substitute(
+                "'{alias}' is already an alias for {command}.",
+                &[("alias", &"{command}"), ("command", &"look")]
+            ),
+            "'{command}' is already an alias for look."
+        );
+    }
+
+    #[test]
+    fn placeholders_without_a_value_are_kept() {
+        assert_eq!(substitute("{unknown} {x", &[("x", &1)]), "{unknown} {x");
+    }
+
+    #[test]
+    fn unknown_messages_are_shown_as_their_id() {
+        assert_eq!(message("no.such-message"), "no.such-message");
+    }
+
+    #[test]
+    fn messages_are_looked_up_where_they_are_spoken() {
+        let mut templates = HashMap::new();
+        templates.insert("restart.cancel".to_string(), "On we go.".to_string());
+        let messages = Messages {
+            templates: Some(Arc::new(templates)),
+            ..Messages::default()
+        };
+
+        {
+            let _speaking = messages.speak();
+            assert_eq!(message("restart.cancel"), "On we go.");
+        }
+        assert_eq!(message("restart.cancel"), english("restart.cancel"));
+    }
+}
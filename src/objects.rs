@@ -0,0 +1,254 @@
+//! Extra kinds of objects read from an object file, so that new items can be added without
+//! recompiling. Object files use the same TOML subset as layouts: each object is an `[[object]]`
+//! table with a one-word `name` and a `description`, and optionally its `category`, its `weight`,
+//! whether it `digs` or `climbs` and its `spawn` chance in percent:
+//!
+//! ```toml
+//! [[object]]
+//! name = "pickaxe"
+//! description = "a pickaxe"
+//! category = "tool"
+//! weight = 4
+//! digs = true
+//! spawn = 10
+//! ```
+//!
+//! The objects read are kept in the options of the game as `CustomObjects`, and each one is then
+//! known as an `Object::Custom` referring to its definition.
+
+use crate::layout::{parse_value, Value};
+use crate::{Category, Object};
+use std::hash::{Hash, Hasher};
+
+/// A kind of object defined in an object file
+#[derive(Debug)]
+pub struct CustomObject {
+    /// The word used to refer to the object in commands
+    pub name: String,
+    /// How a single object is shown, like `a pickaxe`
+    pub description: String,
+    pub category: Category,
+    pub weight: u32,
+    /// Whether the object, when equipped, can dig new rooms
+    pub digs: bool,
+    /// Whether carrying the object lets the player climb up
+    pub climbs: bool,
+    /// Chance of the object to be found in a freshly dug room
+    pub spawn_probability: f32,
+}
+
+// Names are unique among the objects of a file, and so tell them apart
+impl PartialEq for CustomObject {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for CustomObject {}
+
+impl Hash for CustomObject {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+    }
+}
+
+/// The kinds of objects read from an object file. Each game keeps the ones it was set up with,
+/// which live until the program ends so that `Object` can refer to them and still be `Copy`
+#[derive(Clone, Default)]
+pub struct CustomObjects(Vec<&'static CustomObject>);
+
+impl CustomObjects {
+    /// Reads the objects out of the text of an object file, as `parse` does
+    pub fn load(text: &str) -> Result<CustomObjects, String> {
+        Ok(CustomObjects(
+            parse(text)?
+                .into_iter()
+                .map(|object| &*Box::leak(Box::new(object)))
+                .collect(),
+        ))
+    }
+
+    /// All the objects, in the order they were defined
+    pub fn iter(&self) -> impl Iterator<Item = &'static CustomObject> + '_ {
+        self.0.iter().copied()
+    }
+
+    /// The object with the given name, if any
+    pub fn find(&self, name: &str) -> Option<&'static CustomObject> {
+        self.iter().find(|object| object.name == name)
+    }
+}
+
+/// Reads the custom objects out of the text of an object file. Errors tell the line they were
+/// found on
+pub fn parse(text: &str) -> Result<Vec<CustomObject>, String> {
+    let mut objects: Vec<(usize, CustomObject)> = vec![];
+
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line == "[[object]]" {
+            objects.push((
+                number,
+                CustomObject {
+                    name: String::new(),
+                    description: String::new(),
+                    category: Category::Tool,
+                    weight: 1,
+                    digs: false,
+                    climbs: false,
+                    spawn_probability: 0.0,
+                },
+            ));
+            continue;
+        }
+
+        let error = |message: &str| format!("line {}: {}", number, message);
+
+        let (_, object) = objects
+            .last_mut()
+            .ok_or_else(|| error("expected an [[object]] header first"))?;
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| error("expected KEY = VALUE"))?;
+        let (value, rest) = parse_value(value.trim()).map_err(|e| error(&e))?;
+
+        if !rest.trim().is_empty() && !rest.trim().starts_with('#') {
+            return Err(error("unexpected text after the value"));
+        }
+
+        match (key.trim(), value) {
+            ("name", Value::Text(text)) => {
+                let text = text.to_lowercase();
+                if text.is_empty() || text.contains(char::is_whitespace) {
+                    return Err(error("a name is a single word"));
+                }
+                if Object::from_string(&text, &CustomObjects::default()).is_some() {
+                    return Err(error(&format!(
+                        "there is already an object named \"{}\"",
+                        text
+                    )));
+                }
+                object.name = text;
+            }
+            ("description", Value::Text(text)) => object.description = text,
+            ("category", Value::Text(text)) => {
+                object.category = Category::from_string(&text)
+                    .ok_or_else(|| error(&format!("unknown category \"{}\"", text)))?
+            }
+            ("weight", Value::Integer(weight)) if weight >= 0 => object.weight = weight as u32,
+            ("digs", Value::Boolean(digs)) => object.digs = digs,
+            ("climbs", Value::Boolean(climbs)) => object.climbs = climbs,
+            ("spawn", Value::Integer(spawn)) if (0..=100).contains(&spawn) => {
+                object.spawn_probability = spawn as f32 / 100.0
+            }
+            ("name", _)
+            | ("description", _)
+            | ("category", _)
+            | ("weight", _)
+            | ("digs", _)
+            | ("climbs", _)
+            | ("spawn", _) => {
+                return Err(error(&format!(
+                    "wrong type of value for \"{}\"",
+                    key.trim()
+                )))
+            }
+            (key, _) => return Err(error(&format!("unknown key \"{}\"", key))),
+        }
+    }
+
+    let mut parsed: Vec<CustomObject> = vec![];
+    for (line, mut object) in objects {
+        if object.name.is_empty() {
+            return Err(format!("line {}: the object has no name", line));
+        }
+        if parsed.iter().any(|other| other.name == object.name) {
+            return Err(format!(
+                "line {}: there is already an object named \"{}\"",
+                line, object.name
+            ));
+        }
+        if object.description.is_empty() {
+            object.description = format!("a {}", object.name);
+        }
+        parsed.push(object);
+    }
+
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn objects_are_read_with_their_defaults() {
+        let objects = parse(
+            "[[object]]\nname = \"Pickaxe\"\ncategory = \"tool\"\nweight = 4\ndigs = true\n\
+             spawn = 20\n\n[[object]]\nname = \"gem\"\ndescription = \"a shiny gem\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[0].name, "pickaxe");
+        assert_eq!(objects[0].description, "a pickaxe");
+        assert_eq!(objects[0].weight, 4);
+        assert!(objects[0].digs && !objects[0].climbs);
+        assert_eq!(objects[0].spawn_probability, 0.2);
+        assert_eq!(objects[1].description, "a shiny gem");
+        assert_eq!(objects[1].weight, 1);
+        assert_eq!(objects[1].spawn_probability, 0.0);
+    }
+
+    #[test]
+    fn mistakes_are_reported_with_their_line() {
+        for (text, error) in [
+            (
+                "name = \"gem\"",
+                "line 1: expected an [[object]] header first",
+            ),
+            (
+                "[[object]]\nname = \"two words\"",
+                "line 2: a name is a single word",
+            ),
+            (
+                "[[object]]\nname = \"gold\"",
+                "line 2: there is already an object named \"gold\"",
+            ),
+            (
+                "[[object]]\nname = \"gem\"\n[[object]]\nname = \"gem\"",
+                "line 3: there is already an object named \"gem\"",
+            ),
+            (
+                "[[object]]\nweight = \"heavy\"",
+                "line 2: wrong type of value for \"weight\"",
+            ),
+            (
+                "[[object]]\nspawn = 101",
+                "line 2: wrong type of value for \"spawn\"",
+            ),
+            (
+                "[[object]]\ncolor = \"red\"",
+                "line 2: unknown key \"color\"",
+            ),
+            ("[[object]]\ndigs = true", "line 1: the object has no name"),
+        ] {
+            assert_eq!(parse(text).unwrap_err(), error, "{}", text);
+        }
+    }
+
+    #[test]
+    fn loaded_objects_are_found_by_name() {
+        let objects = CustomObjects::load("[[object]]\nname = \"gem\"").unwrap();
+
+        assert_eq!(objects.find("gem").map(|gem| gem.weight), Some(1));
+        assert!(objects.find("pickaxe").is_none());
+        assert!(CustomObjects::default().find("gem").is_none());
+    }
+}
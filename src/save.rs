@@ -0,0 +1,594 @@
+//! Saving a game to a file and resuming it later. Saves use the same TOML subset as layouts: a
+//! `[game]` table with the settings of the game, a `[player]` table, then one `[[room]]` table per
+//! room, one `[[macro]]` table per recorded macro and one `[[alias]]` table per command listing the
+//! words that issue it. Locations are written as lists of three integers, and objects as lists of
+//! names, repeated as many times as there are objects of that kind:
+//!
+//! ```toml
+//! [game]
+//! verbosity = "normal"
+//! goals = [[1, 1, 5]]
+//!
+//! [player]
+//! location = [0, 0, 0]
+//! inventory = ["sledge", "gold", "gold"]
+//! ```
+//!
+//! The random numbers are not saved, so a resumed game draws new ones.
+//!
+//! The `version` of the format is written first, in the `[game]` table. Saves of older versions
+//! are brought up to date on load by running, in order, the `MIGRATIONS` from their version on,
+//! while saves of newer versions are refused. Saves lacking a version are of version 1.
+
+use crate::layout::{as_location, parse_value, Value};
+use crate::objects::CustomObjects;
+use crate::{
+    default_aliases, Achievement, Command, Direction, Dungeon, Game, Inventory, Location, Object,
+    Options, Player, Room, Verbosity,
+};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+use std::time::Duration;
+
+/// The keys of a table, with their values
+type Table = HashMap<String, Value>;
+
+/// Version of the save format written by this build
+const VERSION: u32 = 2;
+
+/// Steps bringing the `[game]` and `[player]` tables of a save up to the next version, the first
+/// one from version 1 to 2
+const MIGRATIONS: [fn(&mut Table, &mut Table); VERSION as usize - 1] = [migrate_v1];
+
+/// Version 1 saves, written before the counters were saved, start them from scratch
+fn migrate_v1(game: &mut Table, player: &mut Table) {
+    let defaults = |table: &mut Table, keys: Vec<(&str, Value)>| {
+        for (key, value) in keys {
+            table.entry(key.to_string()).or_insert(value);
+        }
+    };
+
+    defaults(
+        game,
+        vec![
+            ("achievements", Value::List(vec![])),
+            ("played", Value::Integer(0)),
+        ],
+    );
+    defaults(
+        player,
+        vec![
+            ("moves", Value::Integer(0)),
+            ("hunger", Value::Integer(0)),
+            ("trail", Value::List(vec![])),
+            ("rooms_dug", Value::Integer(0)),
+            ("gold_collected", Value::Integer(0)),
+            ("collected", Value::List(vec![])),
+            ("sledge_uses_left", Value::Integer(0)),
+        ],
+    );
+}
+
+/// Writes out the whole state of a game
+pub fn write(game: &Game) -> String {
+    let mut out = String::from("# A saved game of rcrpg-rust\n\n[game]\n");
+    let mut line = |key: &str, value: String| out.push_str(&format!("{} = {}\n", key, value));
+
+    line("version", VERSION.to_string());
+
+    line("verbosity", text(game.verbosity.name()));
+    line("auto_seal", game.auto_seal.to_string());
+    line("won", game.won.to_string());
+    line(
+        "achievements",
+        list(game.achievements.iter().map(|a| text(a.name()))),
+    );
+    line("played", game.playtime().as_secs().to_string());
+    line("goals", locations(&game.dungeon.goals));
+
+    let player = &game.player;
+    out.push_str("\n[player]\n");
+    let mut line = |key: &str, value: String| out.push_str(&format!("{} = {}\n", key, value));
+    line("location", location(player.location));
+    line("inventory", objects(&player.inventory));
+    if let Some(equipped) = player.equipped {
+        line("equipped", text(equipped.name()));
+    }
+    line(
+        "visited",
+        locations(&player.visited.iter().copied().collect::<Vec<Location>>()),
+    );
+    line("health", player.health.to_string());
+    line("max_health", player.max_health.to_string());
+    line("moves", player.moves.to_string());
+    line("hunger", player.hunger.to_string());
+    line("trail", locations(&player.trail));
+    line("rooms_dug", player.rooms_dug.to_string());
+    line("gold_collected", player.gold_collected.to_string());
+    line(
+        "collected",
+        locations(&player.collected.iter().copied().collect::<Vec<Location>>()),
+    );
+    if let Some(durability) = player.sledge_durability {
+        line("sledge_durability", durability.to_string());
+    }
+    line("sledge_uses_left", player.sledge_uses_left.to_string());
+
+    let mut rooms = game
+        .dungeon
+        .rooms
+        .iter()
+        .collect::<Vec<(&Location, &Room)>>();
+    rooms.sort_by_key(|(location, _)| **location);
+    for (at, room) in rooms {
+        out.push_str("\n[[room]]\n");
+        let mut line = |key: &str, value: String| out.push_str(&format!("{} = {}\n", key, value));
+        line("location", location(*at));
+        if let Some(name) = &room.name {
+            line("name", text(name));
+        }
+        if let Some(description) = &room.description {
+            line("description", text(description));
+        }
+        line("objects", objects(&room.objects));
+        line("hidden", objects(&room.hidden));
+        line("dug", room.dug.to_string());
+        line("pit", room.pit.to_string());
+        line("hazard", room.hazard.to_string());
+        line("forge", room.forge.to_string());
+        line("vendor", room.vendor.to_string());
+        line(
+            "locked",
+            list(room.locked.iter().map(|direction| text(direction.name()))),
+        );
+    }
+
+    let mut macros = game.macros.iter().collect::<Vec<(&String, &Vec<String>)>>();
+    macros.sort();
+    for (name, lines) in macros {
+        out.push_str(&format!(
+            "\n[[macro]]\nname = {}\nlines = {}\n",
+            text(name),
+            list(lines.iter().map(|line| text(line)))
+        ));
+    }
+
+    for (names, command) in &game.command_aliases {
+        let mut names = names.iter().collect::<Vec<&String>>();
+        names.sort();
+        out.push_str(&format!(
+            "\n[[alias]]\ncommand = {}\nnames = {}\n",
+            text(command.name()),
+            list(names.into_iter().map(|name| text(name)))
+        ));
+    }
+
+    out
+}
+
+/// Reads a saved game back, set up otherwise as the options say. Errors tell the line they were
+/// found on, or what is missing
+pub fn read(saved: &str, options: &Options) -> Result<Game, String> {
+    let mut game_table: Option<Table> = None;
+    let mut player_table: Option<Table> = None;
+    let mut rooms: Vec<Table> = vec![];
+    let mut macros: Vec<Table> = vec![];
+    let mut aliases: Vec<Table> = vec![];
+    let mut current: Option<&mut Table> = None;
+
+    for (index, line) in saved.lines().enumerate() {
+        let number = index + 1;
+        let line = line.trim();
+        let error = |message: &str| format!("line {}: {}", number, message);
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match line {
+            "[game]" => current = Some(game_table.get_or_insert_with(Table::new)),
+            "[player]" => current = Some(player_table.get_or_insert_with(Table::new)),
+            "[[room]]" => {
+                rooms.push(Table::new());
+                current = rooms.last_mut();
+            }
+            "[[macro]]" => {
+                macros.push(Table::new());
+                current = macros.last_mut();
+            }
+            "[[alias]]" => {
+                aliases.push(Table::new());
+                current = aliases.last_mut();
+            }
+            _ => {
+                let table = current
+                    .as_mut()
+                    .ok_or_else(|| error("expected a table header first"))?;
+                let (key, value) = line
+                    .split_once('=')
+                    .ok_or_else(|| error("expected KEY = VALUE"))?;
+                let (value, rest) = parse_value(value.trim()).map_err(|e| error(&e))?;
+
+                if !rest.trim().is_empty() {
+                    return Err(error("unexpected text after the value"));
+                }
+                table.insert(key.trim().to_string(), value);
+            }
+        }
+    }
+
+    let mut game_table = game_table.ok_or("the [game] table is missing")?;
+    let mut player_table = player_table.ok_or("the [player] table is missing")?;
+
+    let version = get_optional(&game_table, "version", get_count)?.unwrap_or(1);
+    if !(1..=VERSION).contains(&version) {
+        return Err(format!(
+            "the save is of version {}, but this game only reads saves up to version {}",
+            version, VERSION
+        ));
+    }
+    for migrate in &MIGRATIONS[version as usize - 1..] {
+        migrate(&mut game_table, &mut player_table);
+    }
+    let mut game = Game::new(options);
+    let objects = &options.generation.objects;
+
+    game.verbosity =
+        Verbosity::from_string(&get_text(&game_table, "verbosity")?).ok_or("unknown verbosity")?;
+    game.auto_seal = get_boolean(&game_table, "auto_seal")?;
+    game.won = get_boolean(&game_table, "won")?;
+    game.achievements = get_texts(&game_table, "achievements")?
+        .iter()
+        .map(|name| Achievement::from_string(name).ok_or(format!("unknown achievement {}", name)))
+        .collect::<Result<HashSet<Achievement>, String>>()?;
+    game.played = Duration::from_secs(get_count(&game_table, "played")?.into());
+
+    let mut dungeon = Dungeon {
+        rooms: HashMap::new(),
+        goals: get_locations(&game_table, "goals")?,
+        others: vec![],
+    };
+    for table in &rooms {
+        let at = get_location(table, "location")?;
+        let mut room = Room::new();
+        room.name = get_optional(table, "name", get_text)?;
+        room.description = get_optional(table, "description", get_text)?;
+        room.objects = get_objects(table, "objects", objects)?;
+        room.hidden = get_objects(table, "hidden", objects)?;
+        room.dug = get_boolean(table, "dug")?;
+        room.pit = get_boolean(table, "pit")?;
+        room.hazard = get_boolean(table, "hazard")?;
+        room.forge = get_boolean(table, "forge")?;
+        room.vendor = get_boolean(table, "vendor")?;
+        room.locked = get_texts(table, "locked")?
+            .iter()
+            .map(|name| Direction::from_string(name).ok_or(format!("unknown direction {}", name)))
+            .collect::<Result<HashSet<Direction>, String>>()?;
+
+        if dungeon.rooms.insert(at, room).is_some() {
+            return Err(format!("there are two rooms at {:?}", at));
+        }
+    }
+    game.dungeon = dungeon;
+
+    let table = &player_table;
+    let mut player = Player::new(
+        get_integer(table, "max_health")?,
+        get_objects(table, "inventory", objects)?,
+        get_optional(table, "sledge_durability", get_count)?,
+    );
+    player.location = get_location(table, "location")?;
+    if !game.dungeon.rooms.contains_key(&player.location) {
+        return Err(format!(
+            "the player is at {:?}, where there is no room",
+            player.location
+        ));
+    }
+    player.equipped = get_optional(table, "equipped", get_text)?
+        .map(|name| Object::from_string(&name, objects).ok_or(format!("unknown object {}", name)))
+        .transpose()?;
+    player.visited = HashSet::from_iter(get_locations(table, "visited")?);
+    player.health = get_integer(table, "health")?;
+    if player.health > player.max_health {
+        return Err(format!(
+            "the player has {} health, more than the most of {}",
+            player.health, player.max_health
+        ));
+    }
+    player.moves = get_count(table, "moves")?;
+    player.hunger = get_count(table, "hunger")?;
+    player.trail = get_locations(table, "trail")?;
+    player.rooms_dug = get_count(table, "rooms_dug")?;
+    player.gold_collected = get_count(table, "gold_collected")?;
+    player.collected = HashSet::from_iter(get_locations(table, "collected")?);
+    player.sledge_uses_left = get_count(table, "sledge_uses_left")?;
+    game.player = player;
+
+    for table in &macros {
+        game.macros
+            .insert(get_text(table, "name")?, get_texts(table, "lines")?);
+    }
+
+    if !aliases.is_empty() {
+        let commands = default_aliases()
+            .into_iter()
+            .map(|(_, command)| command)
+            .collect::<Vec<Command>>();
+
+        game.command_aliases = aliases
+            .iter()
+            .map(|table| {
+                let name = get_text(table, "command")?;
+                let command = commands
+                    .iter()
+                    .find(|command| command.name() == name)
+                    .ok_or(format!("unknown command {}", name))?;
+
+                Ok((HashSet::from_iter(get_texts(table, "names")?), *command))
+            })
+            .collect::<Result<Vec<(HashSet<String>, Command)>, String>>()?;
+    }
+
+    Ok(game)
+}
+
+/// Quotes and escapes a string the way `parse_value` reads it back
+fn text(s: &str) -> String {
+    format!(
+        "\"{}\"",
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+/// Writes already formatted values as a list
+fn list(values: impl Iterator<Item = String>) -> String {
+    format!("[{}]", values.collect::<Vec<String>>().join(", "))
+}
+
+fn location(location: Location) -> String {
+    format!("[{}, {}, {}]", location.0, location.1, location.2)
+}
+
+/// Writes locations as a list, sorted so that the same state always gives the same save
+fn locations(locations: &[Location]) -> String {
+    let mut locations = locations.to_vec();
+    locations.sort();
+    list(locations.into_iter().map(location))
+}
+
+/// Writes the objects as a list of names, each repeated as many times as there are objects
+fn objects(inventory: &Inventory) -> String {
+    list(
+        inventory
+            .iter()
+            .flat_map(|(object, count)| std::iter::repeat_n(text(object.name()), count as usize)),
+    )
+}
+
+/// Reads a value that may be left out
+fn get_optional<T>(
+    table: &Table,
+    key: &str,
+    get: fn(&Table, &str) -> Result<T, String>,
+) -> Result<Option<T>, String> {
+    if table.contains_key(key) {
+        get(table, key).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Reads a value that must be there
+fn get<'a>(table: &'a Table, key: &str) -> Result<&'a Value, String> {
+    table.get(key).ok_or(format!("\"{}\" is missing", key))
+}
+
+fn get_integer(table: &Table, key: &str) -> Result<i32, String> {
+    match get(table, key)? {
+        Value::Integer(integer) => Ok(*integer),
+        _ => Err(format!("\"{}\" should be an integer", key)),
+    }
+}
+
+/// Reads an integer that can't be negative
+fn get_count(table: &Table, key: &str) -> Result<u32, String> {
+    u32::try_from(get_integer(table, key)?)
+        .map_err(|_| format!("\"{}\" should not be negative", key))
+}
+
+fn get_boolean(table: &Table, key: &str) -> Result<bool, String> {
+    match get(table, key)? {
+        Value::Boolean(boolean) => Ok(*boolean),
+        _ => Err(format!("\"{}\" should be true or false", key)),
+    }
+}
+
+fn get_text(table: &Table, key: &str) -> Result<String, String> {
+    match get(table, key)? {
+        Value::Text(text) => Ok(text.clone()),
+        _ => Err(format!("\"{}\" should be a string", key)),
+    }
+}
+
+fn get_texts(table: &Table, key: &str) -> Result<Vec<String>, String> {
+    match get(table, key)? {
+        Value::List(values) => values
+            .iter()
+            .map(|value| match value {
+                Value::Text(text) => Ok(text.clone()),
+                _ => Err(format!("\"{}\" should be a list of strings", key)),
+            })
+            .collect(),
+        _ => Err(format!("\"{}\" should be a list of strings", key)),
+    }
+}
+
+/// Reads a location written as a list of three integers
+fn get_location(table: &Table, key: &str) -> Result<Location, String> {
+    as_location(get(table, key)?).ok_or(format!("\"{}\" should be a location", key))
+}
+
+fn get_locations(table: &Table, key: &str) -> Result<Vec<Location>, String> {
+    let invalid = || format!("\"{}\" should be a list of locations", key);
+
+    match get(table, key)? {
+        Value::List(values) => values
+            .iter()
+            .map(|value| as_location(value).ok_or_else(invalid))
+            .collect(),
+        _ => Err(invalid()),
+    }
+}
+
+fn get_objects(table: &Table, key: &str, objects: &CustomObjects) -> Result<Inventory, String> {
+    get_texts(table, key)?
+        .iter()
+        .map(|name| Object::from_string(name, objects).ok_or(format!("unknown object {}", name)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_saved_game_loads_back_the_same() {
+        let options = Options::default();
+        let mut game = Game::new(&options);
+        for line in [
+            "take ladder",
+            "equip sledge",
+            "dig east",
+            "east",
+            "name Cellar",
+            "record tour",
+            "west",
+            "east",
+            "end",
+            "alias inventory stuff",
+        ] {
+            game.step(line);
+        }
+
+        let saved = write(&game);
+        let loaded = read(&saved, &options).unwrap();
+
+        assert_eq!(write(&loaded), saved);
+        assert_eq!(loaded.player.location, Location(1, 0, 0));
+        assert_eq!(loaded.dungeon.rooms.len(), game.dungeon.rooms.len());
+        assert_eq!(
+            loaded.dungeon.rooms[&Location(1, 0, 0)].name.as_deref(),
+            Some("Cellar")
+        );
+        assert!(loaded.player.inventory.contains(Object::Ladder));
+        assert!(loaded.player.inventory.contains(Object::Sledge));
+        assert_eq!(
+            loaded.macros["tour"],
+            vec!["west".to_string(), "east".to_string()]
+        );
+        assert!(loaded
+            .command_aliases
+            .iter()
+            .any(|(aliases, command)| *command == Command::Inventory && aliases.contains("stuff")));
+    }
+
+    /// A save of version 1, from before the version was written and the player's statistics saved
+    const V1: &str = "[game]
+verbosity = \"normal\"
+auto_seal = false
+won = false
+goals = [[1, 1, 5]]
+
+[player]
+location = [0, 0, 0]
+inventory = [\"sledge\", \"gold\", \"gold\"]
+visited = [[0, 0, 0]]
+health = 7
+max_health = 10
+
+[[room]]
+location = [0, 0, 0]
+objects = [\"ladder\"]
+hidden = []
+dug = false
+pit = false
+hazard = false
+forge = false
+vendor = false
+locked = []
+";
+
+    #[test]
+    fn a_save_of_version_1_is_brought_up_to_date() {
+        let game = read(V1, &Options::default()).unwrap();
+
+        assert_eq!(game.player.health, 7);
+        assert_eq!(game.player.inventory.count(Object::Gold), 2);
+        assert_eq!(game.player.moves, 0);
+        assert_eq!(game.player.sledge_uses_left, 0);
+        assert!(game.achievements.is_empty());
+        assert_eq!(game.played, Duration::from_secs(0));
+        assert!(write(&game).contains(&format!("version = {}\n", VERSION)));
+    }
+
+    #[test]
+    fn counts_out_of_range_are_refused() {
+        for (from, to, error) in [
+            (
+                "health = 7",
+                "health = 7\nmoves = -1",
+                "\"moves\" should not be negative",
+            ),
+            (
+                "[game]",
+                "[game]\nplayed = -5",
+                "\"played\" should not be negative",
+            ),
+            (
+                "health = 7",
+                "health = 7\nsledge_durability = -2",
+                "\"sledge_durability\" should not be negative",
+            ),
+            (
+                "[game]",
+                "[game]\nversion = -1",
+                "\"version\" should not be negative",
+            ),
+            (
+                "health = 7",
+                "health = 11",
+                "the player has 11 health, more than the most of 10",
+            ),
+        ] {
+            let saved = V1.replace(from, to);
+            assert_eq!(
+                read(&saved, &Options::default()).err().as_deref(),
+                Some(error),
+                "{}",
+                to
+            );
+        }
+
+        let saved = V1.replace("health = 7", "health = 7\nmoves = 4294967296");
+        assert!(read(&saved, &Options::default())
+            .err()
+            .unwrap()
+            .starts_with("line 12: "));
+    }
+
+    #[test]
+    fn a_save_placing_the_player_where_there_is_no_room_is_refused() {
+        let saved = V1.replace(
+            "location = [0, 0, 0]\ninventory",
+            "location = [5, 5, 5]\ninventory",
+        );
+
+        assert_eq!(
+            read(&saved, &Options::default()).err().as_deref(),
+            Some("the player is at (5, 5, 5), where there is no room")
+        );
+    }
+}
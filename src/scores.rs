@@ -0,0 +1,188 @@
+//! The high-score table, kept in a plain text file with one won game per line: its score, the seed
+//! of its random numbers and the moves it took, separated by spaces. Only the best `TOP_SCORES`
+//! games are kept, the best first.
+
+use crate::messages::{message, message_with};
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+
+/// How many games the table keeps
+pub const TOP_SCORES: usize = 5;
+
+/// A won game, as recorded in the table
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Score {
+    pub score: u32,
+    /// Seed to play the same dungeon again with `--seed`
+    pub seed: u64,
+    pub moves: u32,
+}
+
+/// Reads the table from the file at `path`. A missing file is an empty table, and lines that
+/// can't be read are left out with a warning, so that a damaged file doesn't stop the game
+pub fn load(path: &str) -> Vec<Score> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return vec![],
+        Err(error) => {
+            eprintln!(
+                "{}",
+                message_with("scores.cannot-read", &[("path", &path), ("error", &error)])
+            );
+            return vec![];
+        }
+    };
+
+    let mut scores = vec![];
+    for (index, line) in text.lines().enumerate() {
+        match parse(line) {
+            Some(score) => {
+                insert(&mut scores, score);
+            }
+            None => eprintln!(
+                "{}",
+                message_with(
+                    "scores.not-a-score",
+                    &[("line", &(index + 1)), ("path", &path)]
+                )
+            ),
+        }
+    }
+
+    scores
+}
+
+/// Reads a line of the table, like `230 42 57`
+fn parse(line: &str) -> Option<Score> {
+    let fields = line.split_whitespace().collect::<Vec<&str>>();
+
+    match fields.as_slice() {
+        [score, seed, moves] => Some(Score {
+            score: score.parse().ok()?,
+            seed: seed.parse().ok()?,
+            moves: moves.parse().ok()?,
+        }),
+        _ => None,
+    }
+}
+
+/// Puts a score in its place in the table, higher scores first and then fewer moves, and drops
+/// the ones falling past `TOP_SCORES`. Returns whether the score made it into the table
+pub fn insert(scores: &mut Vec<Score>, score: Score) -> bool {
+    let key = |score: &Score| (Reverse(score.score), score.moves);
+    let position = scores.partition_point(|other| key(other) <= key(&score));
+
+    scores.insert(position, score);
+    scores.truncate(TOP_SCORES);
+    position < TOP_SCORES
+}
+
+/// Adds a won game to the table in the file at `path`. Returns whether it made it into the table
+pub fn record(path: &str, score: Score) -> io::Result<bool> {
+    let mut scores = load(path);
+    let placed = insert(&mut scores, score);
+    let text = scores
+        .iter()
+        .map(|score| format!("{} {} {}\n", score.score, score.seed, score.moves))
+        .collect::<String>();
+
+    fs::write(path, text)?;
+    Ok(placed)
+}
+
+/// The table as shown to the player
+pub fn table(scores: &[Score]) -> String {
+    let mut lines = vec![format!(
+        "{:<4} {:>6} {:>6}  {}",
+        message("scores.rank"),
+        message("scores.score"),
+        message("scores.moves"),
+        message("scores.seed")
+    )];
+
+    for (rank, score) in scores.iter().enumerate() {
+        lines.push(format!(
+            "{:<4} {:>6} {:>6}  {}",
+            rank + 1,
+            score.score,
+            score.moves,
+            score.seed
+        ));
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(score: u32, moves: u32) -> Score {
+        Score {
+            score,
+            seed: 1,
+            moves,
+        }
+    }
+
+    #[test]
+    fn higher_scores_and_then_fewer_moves_come_first() {
+        let mut scores = vec![];
+
+        assert!(insert(&mut scores, score(100, 50)));
+        assert!(insert(&mut scores, score(200, 80)));
+        assert!(insert(&mut scores, score(100, 30)));
+        assert!(insert(&mut scores, score(100, 50)));
+
+        assert_eq!(
+            scores,
+            vec![
+                score(200, 80),
+                score(100, 30),
+                score(100, 50),
+                score(100, 50)
+            ]
+        );
+    }
+
+    #[test]
+    fn only_the_top_scores_are_kept() {
+        let mut scores = vec![];
+        for points in 1..=TOP_SCORES as u32 {
+            assert!(insert(&mut scores, score(points * 10, 20)));
+        }
+
+        assert!(!insert(&mut scores, score(5, 20)));
+        assert!(!insert(&mut scores, score(10, 25)));
+        assert_eq!(scores.len(), TOP_SCORES);
+        assert_eq!(scores.last(), Some(&score(10, 20)));
+
+        assert!(insert(&mut scores, score(1000, 99)));
+        assert_eq!(scores.len(), TOP_SCORES);
+        assert_eq!(scores[0], score(1000, 99));
+        assert_eq!(scores.last(), Some(&score(20, 20)));
+    }
+
+    #[test]
+    fn lines_of_the_table_are_read_back() {
+        assert_eq!(
+            parse("230 42 57"),
+            Some(Score {
+                score: 230,
+                seed: 42,
+                moves: 57
+            })
+        );
+        assert_eq!(parse("230 42"), None);
+        assert_eq!(parse("230 -42 57"), None);
+    }
+
+    #[test]
+    fn the_table_shows_a_line_per_score_under_its_headings() {
+        assert_eq!(
+            table(&[score(230, 57), score(120, 80)]),
+            "#     Score  Moves  Seed\n1       230     57  1\n2       120     80  1"
+        );
+    }
+}
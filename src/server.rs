@@ -0,0 +1,284 @@
+//! Playing over the network, MUD style. The server listens on a TCP socket and plays an
+//! independent game with each client that connects. The protocol is plain newline-delimited text:
+//! the client sends one command per line, and the server answers with the text `Game::step`
+//! returns, followed by the prompt. The connection is closed when the game ends or the client
+//! goes away, so `telnet` or `nc` are enough to play.
+//!
+//! With `--shared` all the clients explore the same dungeon instead, each with their own position
+//! and inventory: rooms dug by one can be walked into by the others, who are told when someone
+//! walks in or out of the room they are in, or gives them something with `give`. Such messages can
+//! come at any time, between answers.
+
+use crate::messages::{message, message_with};
+use crate::{Dungeon, Game, Inventory, Location, Options};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+/// What the players of a shared dungeon have in common
+struct World {
+    dungeon: Dungeon,
+    /// Where each player is and how to reach them, by connection number
+    players: HashMap<usize, Presence>,
+}
+
+/// A player of a shared dungeon, as the others see them
+struct Presence {
+    /// Where the player stands
+    location: Location,
+    /// What the others call the player
+    name: String,
+    /// Where to send text to the player
+    sender: Sender<String>,
+    /// Objects given to the player, which go into their inventory before their next command
+    received: Inventory,
+}
+
+impl World {
+    /// Sends a line to every player standing at `location`, except the one with the given number
+    fn tell(&self, location: Location, except: usize, message: &str) {
+        for (id, player) in &self.players {
+            if *id != except && player.location == location {
+                // A player who just left will be cleaned up by their own thread
+                let _ = player.sender.send(format!("{}\n", message));
+            }
+        }
+    }
+}
+
+/// Serves games on `address` until the listener fails, each connection in its own thread
+pub fn serve(address: &str, options: Options) -> io::Result<()> {
+    serve_on(TcpListener::bind(address)?, options)
+}
+
+/// Serves games to the connections coming to `listener` until it fails, each in its own thread
+pub fn serve_on(listener: TcpListener, mut options: Options) -> io::Result<()> {
+    options.autosave_path = None;
+    options.scores_path = None;
+    let _speaking = options.messages.speak();
+    let world = if options.shared {
+        let mut game = Game::new(&options);
+        if let Some(path) = options.map() {
+            if let Err(error) = game.load_map(path) {
+                eprintln!("{}\n{}", error, message("restart.default-dungeon"));
+            }
+        }
+
+        Some(Arc::new(Mutex::new(World {
+            dungeon: game.dungeon,
+            players: HashMap::new(),
+        })))
+    } else {
+        None
+    };
+    let options = Arc::new(options);
+    eprintln!(
+        "{}",
+        message_with("server.serving", &[("address", &listener.local_addr()?)])
+    );
+
+    for (id, stream) in listener.incoming().enumerate() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                eprintln!(
+                    "{}",
+                    message_with("server.cannot-accept", &[("error", &error)])
+                );
+                continue;
+            }
+        };
+        let options = Arc::clone(&options);
+        let world = world.clone();
+
+        thread::spawn(move || {
+            let _speaking = options.messages.speak();
+            let peer = stream
+                .peer_addr()
+                .map(|address| address.to_string())
+                .unwrap_or_else(|_| message("server.unknown-client"));
+
+            eprintln!("{}", message_with("server.connected", &[("peer", &peer)]));
+            let result = match world {
+                Some(world) => play_shared(stream, &options, &world, id),
+                None => play(stream, &options),
+            };
+            match result {
+                Ok(()) => eprintln!(
+                    "{}",
+                    message_with("server.disconnected", &[("peer", &peer)])
+                ),
+                Err(error) => eprintln!(
+                    "{}",
+                    message_with("server.dropped", &[("peer", &peer), ("error", &error)])
+                ),
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Plays a whole game with a single client. The game is thrown away when the client leaves
+fn play(stream: TcpStream, options: &Options) -> io::Result<()> {
+    let mut game = Game::new(options);
+    if let Some(path) = options.map() {
+        if let Err(error) = game.load_map(path) {
+            eprintln!("{}\n{}", error, message("restart.default-dungeon"));
+        }
+    }
+
+    let input = BufReader::new(stream.try_clone()?);
+    let mut output = stream;
+    game.run(input, &mut output)
+}
+
+/// Plays in the shared dungeon with a single client, numbered `id`. Everything sent to the client
+/// goes through a channel, so that the other players' threads can write to it too
+fn play_shared(
+    stream: TcpStream,
+    options: &Options,
+    world: &Mutex<World>,
+    id: usize,
+) -> io::Result<()> {
+    let (sender, receiver) = mpsc::channel::<String>();
+    let mut output = stream.try_clone()?;
+    let writer = thread::spawn(move || {
+        for text in receiver {
+            if output.write_all(text.as_bytes()).is_err() || output.flush().is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut game = Game::new(options);
+    game.shared = true;
+    game.number = id + 1;
+    {
+        let mut world = lock(world);
+        game.dungeon.goals = world.dungeon.goals.clone();
+        world.tell(
+            game.player.location,
+            id,
+            &message_with("shared.joins", &[("name", &game.name())]),
+        );
+        world.players.insert(
+            id,
+            Presence {
+                location: game.player.location,
+                name: game.name(),
+                sender: sender.clone(),
+                received: Inventory::default(),
+            },
+        );
+    }
+    let _ = sender.send(format!("{}\n{}", game.intro(), game.prompt));
+
+    let mut result = Ok(());
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(error) => {
+                result = Err(error);
+                break;
+            }
+        };
+
+        let mut world = lock(world);
+        let before = game.player.location;
+        let name = game.name();
+
+        if let Some(player) = world.players.get_mut(&id) {
+            game.player.inventory.append(&mut player.received);
+        }
+        world.dungeon.others = world
+            .players
+            .iter()
+            .filter(|(other, _)| **other != id)
+            .map(|(_, player)| (player.location, player.name.clone()))
+            .collect();
+        let snapshot = world.dungeon.clone();
+        std::mem::swap(&mut game.dungeon, &mut world.dungeon);
+        // A command going wrong ends this player's game, and gives the others back the dungeon as
+        // it was before the command
+        let output = panic::catch_unwind(AssertUnwindSafe(|| game.step(&line)));
+        std::mem::swap(&mut game.dungeon, &mut world.dungeon);
+        let output = match output {
+            Ok(output) => output,
+            Err(_) => {
+                world.dungeon = snapshot;
+                result = Err(io::Error::other("the game crashed"));
+                break;
+            }
+        };
+
+        let after = game.player.location;
+        if let Some(player) = world.players.get_mut(&id) {
+            player.location = after;
+            player.name = game.name();
+        }
+        if after != before {
+            world.tell(
+                before,
+                id,
+                &message_with("shared.leaves-room", &[("name", &name)]),
+            );
+            world.tell(
+                after,
+                id,
+                &message_with("shared.walks-in", &[("name", &name)]),
+            );
+        }
+        for (recipient, object, count) in game.given.drain(..) {
+            // The recipient was in the room when the command ran, and the lock was held since
+            if let Some(player) = world
+                .players
+                .values_mut()
+                .find(|player| player.name == recipient)
+            {
+                player.received.add(object, count);
+                let _ = player.sender.send(format!(
+                    "{}\n",
+                    message_with(
+                        "shared.gives",
+                        &[("name", &name), ("objects", &object.display_count(count))]
+                    )
+                ));
+            }
+        }
+        drop(world);
+
+        if !output.is_empty() {
+            let _ = sender.send(format!("{}\n", output));
+        }
+        if game.over {
+            break;
+        }
+        let _ = sender.send(game.prompt.clone());
+    }
+
+    {
+        let mut world = lock(world);
+        world.players.remove(&id);
+        world.tell(
+            game.player.location,
+            id,
+            &message_with("shared.leaves", &[("name", &game.name())]),
+        );
+    }
+    drop(sender);
+    let _ = writer.join();
+
+    result
+}
+
+/// Takes the lock on the world, even if a thread panicked while holding it. Commands that panic
+/// are caught and their changes to the dungeon undone (see `play_shared`), so the other players
+/// go on with the world as it is
+fn lock(world: &Mutex<World>) -> MutexGuard<'_, World> {
+    world.lock().unwrap_or_else(PoisonError::into_inner)
+}
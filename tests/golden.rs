@@ -0,0 +1,93 @@
+//! Plays fixed scripts through the whole game loop and compares everything written with the
+//! transcripts in `tests/golden`. The seed is fixed so the random objects are always the same.
+//!
+//! These tests fail whenever a message the player sees changes. When the change is intended,
+//! update the transcript to the new output.
+
+use rcrpg_rust::{Game, Options};
+use std::io::{Cursor, Write};
+use std::process::{Command, Stdio};
+
+/// Digs a room below the first one and looks around in it
+const DIG_DOWN: &str = "equip sledge\ndig down\ndown\nlook\ninventory\n";
+
+/// Everything written while playing `script` in a game started with `--seed 1`
+fn play(script: &str) -> String {
+    let options = Options::from_args(["--seed", "1"].iter().map(|flag| flag.to_string())).unwrap();
+    let mut game = Game::new(&options);
+    game.set_prompt("");
+
+    let mut output = vec![];
+    game.run(Cursor::new(script), &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn digging_down_and_looking_around() {
+    assert_eq!(play(DIG_DOWN), include_str!("golden/dig_down.txt"));
+}
+
+/// Everything the game prints when run with `flags` and `script` on its standard input
+fn run_binary(flags: &[&str], script: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rcrpg-rust"))
+        .args(flags)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(script.as_bytes())
+        .unwrap();
+
+    String::from_utf8(child.wait_with_output().unwrap().stdout).unwrap()
+}
+
+#[test]
+fn color_never_prints_the_plain_transcript() {
+    let plain = include_str!("golden/dig_down.txt");
+
+    assert_eq!(
+        run_binary(&["--seed", "1", "--color=never"], DIG_DOWN),
+        plain
+    );
+
+    let colored = run_binary(&["--seed", "1", "--color=always"], DIG_DOWN);
+    assert_ne!(colored, plain);
+    assert!(colored.contains('\u{1b}'));
+}
+
+#[test]
+fn a_script_digging_to_the_prize_wins_the_game() {
+    // The high scores are written in the current directory, so play away from the sources
+    let directory = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("script-victory");
+    std::fs::create_dir_all(&directory).unwrap();
+    let script = directory.join("to-the-prize.txt");
+    std::fs::write(
+        &script,
+        "# Dig to the prize two rooms east\n\nequip sledge\ndig east\neast\n\neast\n# Grab it\ntake gold\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rcrpg-rust"))
+        .args([
+            "--seed",
+            "1",
+            "--goal",
+            "2,0,0",
+            "--color=never",
+            "--script",
+        ])
+        .arg(&script)
+        .current_dir(&directory)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+
+    let output = String::from_utf8(output.stdout).unwrap();
+    assert!(output.contains("Congratulations, you won!"));
+    assert!(output.contains("There is now an exit eastward"));
+    assert!(!output.contains("I don't know what you mean."));
+}
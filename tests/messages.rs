@@ -0,0 +1,69 @@
+//! Plays games with language and templates files, each game showing the messages of its own options
+
+use rcrpg_rust::{Game, Options};
+use std::fs;
+use std::path::PathBuf;
+
+/// Writes `text` to a file named `name` in the scratch directory of the tests, returning its path
+fn scratch_file(name: &str, text: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, text).unwrap();
+    path
+}
+
+/// A game set up with `flags`
+fn game(flags: &[&str]) -> Game {
+    let args = ["--seed", "1"]
+        .iter()
+        .chain(flags)
+        .map(|arg| arg.to_string());
+
+    Game::new(&Options::from_args(args).unwrap())
+}
+
+#[test]
+fn looking_around_in_french() {
+    let french = scratch_file(
+        "french.toml",
+        "room.at = \"Salle en {location}.\"\nroom.no-exits = \"Il n'y a aucune sortie ici.\"\n",
+    );
+
+    let mut game = game(&["--debug", "--lang", french.to_str().unwrap()]);
+    game.step("goto 5 5 5");
+    let look = game.step("look");
+
+    assert!(look.contains("Salle en (5, 5, 5). Il n'y a aucune sortie ici.\n"));
+    // Messages the table lacks are shown in English
+    assert!(look.contains("Compass: "));
+}
+
+#[test]
+fn templates_change_the_warning_about_digging_twice() {
+    let templates = scratch_file(
+        "templates.toml",
+        "error.exit-already-exists = \"A tunnel already leads {direction} from here.\"\n",
+    );
+
+    let mut game = game(&["--templates", templates.to_str().unwrap()]);
+    game.step("equip sledge");
+    game.step("dig down");
+
+    assert_eq!(
+        game.step("dig down"),
+        "A tunnel already leads down from here."
+    );
+}
+
+#[test]
+fn games_in_different_languages_are_played_side_by_side() {
+    let french = scratch_file("side-by-side.toml", "room.no-exits = \"Aucune sortie.\"\n");
+
+    let mut french = game(&["--debug", "--lang", french.to_str().unwrap()]);
+    let mut english = game(&["--debug"]);
+    french.step("goto 5 5 5");
+    english.step("goto 5 5 5");
+
+    assert!(french.step("look").contains("Aucune sortie."));
+    assert!(!english.step("look").contains("Aucune sortie."));
+    assert!(french.step("look").contains("Aucune sortie."));
+}
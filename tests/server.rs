@@ -0,0 +1,188 @@
+use rcrpg_rust::{server, Options};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+/// A player connected to the server
+struct Client {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Client {
+    fn connect(address: SocketAddr) -> Client {
+        let stream = TcpStream::connect(address).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+
+        Client {
+            reader: BufReader::new(stream.try_clone().unwrap()),
+            writer: stream,
+        }
+    }
+
+    fn send(&mut self, line: &str) {
+        writeln!(self.writer, "{}", line).unwrap();
+    }
+
+    /// Reads lines until one contains `text`, failing if the server goes quiet first
+    fn expect(&mut self, text: &str) -> String {
+        let mut received = String::new();
+
+        loop {
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => panic!("disconnected waiting for {:?}, got:\n{}", text, received),
+                Ok(_) => {}
+                Err(error) => panic!("{} waiting for {:?}, got:\n{}", error, text, received),
+            }
+
+            received.push_str(&line);
+            if line.contains(text) {
+                return received;
+            }
+        }
+    }
+}
+
+/// Starts a server with the given flags in the background, returning where to connect to
+fn start(flags: &[&str]) -> SocketAddr {
+    let options = Options::from_args(flags.iter().map(|flag| flag.to_string())).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    thread::spawn(move || server::serve_on(listener, options));
+    address
+}
+
+#[test]
+fn a_player_can_look_around() {
+    let address = start(&["--serve", "127.0.0.1:0", "--seed", "1", "--color=never"]);
+    let mut client = Client::connect(address);
+    client.expect("Have fun!");
+
+    client.send("look");
+    let look = client.expect("Compass:");
+    assert!(look.contains("The room where it all started..."));
+    assert!(look.contains("On the floor you can see: a ladder, a sledge."));
+}
+
+#[test]
+fn the_server_keeps_serving_after_a_player_leaves() {
+    let address = start(&["--serve", "127.0.0.1:0", "--seed", "1", "--color=never"]);
+    let mut first = Client::connect(address);
+    first.expect("Have fun!");
+    first.send("take sledge");
+    first.expect("Taken");
+    drop(first);
+
+    let mut second = Client::connect(address);
+    second.expect("Have fun!");
+    second.send("look");
+    assert!(second.expect("Compass:").contains("a ladder, a sledge."));
+}
+
+/// Starts a shared dungeon and connects two players to it, both standing in the first room
+fn start_shared() -> (Client, Client) {
+    let address = start(&[
+        "--serve",
+        "127.0.0.1:0",
+        "--shared",
+        "--seed",
+        "1",
+        "--color=never",
+    ]);
+
+    let mut first = Client::connect(address);
+    first.expect("Have fun!");
+    let mut second = Client::connect(address);
+    second.expect("Have fun!");
+    first.expect("Player 2 climbs into the dungeon.");
+
+    (first, second)
+}
+
+#[test]
+fn players_see_the_rooms_dug_by_others() {
+    let (mut digger, mut watcher) = start_shared();
+
+    digger.send("equip sledge");
+    digger.expect("Item equipped");
+    digger.send("dig north");
+    digger.expect("There is now an exit northward");
+
+    watcher.send("look");
+    let look = watcher.expect("There is one exit: north.");
+    assert!(look.contains("Player 1 is here."));
+
+    watcher.send("north");
+    digger.expect("Player 2 leaves the room.");
+}
+
+#[test]
+fn rooms_with_other_players_in_them_cannot_be_dug_or_filled() {
+    let (mut digger, mut walker) = start_shared();
+
+    digger.send("equip sledge");
+    digger.expect("Item equipped");
+    digger.send("dig north");
+    digger.expect("There is now an exit northward");
+    walker.send("north");
+    digger.expect("Player 2 leaves the room.");
+
+    digger.send("fill north");
+    digger.expect("Someone's standing in the way.");
+    digger.send("dig north");
+    digger.expect("Someone's standing in the way.");
+
+    walker.send("look");
+    walker.expect("There is one exit: south.");
+    walker.send("south");
+    walker.expect("Room at (0, 0, 0).");
+}
+
+#[test]
+fn players_are_known_by_the_names_they_pick() {
+    let (mut first, mut second) = start_shared();
+
+    first.send("whoami");
+    first.expect("You are Player 1, player 1.");
+    first.send("rename Ann");
+    first.expect("You are now known as Ann.");
+    first.send("whoami");
+    first.expect("You are Ann, player 1.");
+
+    second.send("rename ann");
+    second.expect("ann is already someone else's name.");
+    second.send("look");
+    assert!(second.expect("Compass:").contains("Ann is here."));
+    second.send("rename Bob");
+    second.expect("You are now known as Bob.");
+
+    first.send("look");
+    assert!(first.expect("Compass:").contains("Bob is here."));
+}
+
+#[test]
+fn players_can_give_objects_to_each_other() {
+    let (mut giver, mut taker) = start_shared();
+
+    giver.send("give \"player 2\" rope");
+    giver.expect("You don't have anything like that");
+    giver.send("equip sledge");
+    giver.expect("Item equipped");
+    giver.send("give \"Player 2\" sledge");
+    giver.expect("You give a sledge to Player 2. Your hands are empty now");
+    taker.expect("Player 1 gives you a sledge.");
+
+    taker.send("equip sledge");
+    taker.expect("Item equipped");
+    taker.send("dig north");
+    taker.expect("There is now an exit northward");
+    taker.send("north");
+    giver.expect("Player 2 leaves the room.");
+    giver.send("give \"Player 2\" ladder");
+    giver.expect("There is no player 2 here to give anything to.");
+}